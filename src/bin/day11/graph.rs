@@ -0,0 +1,469 @@
+use std::collections::{HashMap, VecDeque};
+
+use itertools::Either;
+
+use crate::pebbles::{BlinkError, RuleSet, Stone};
+
+/// Largest closed set for which `count_mod` raises the dense matrix directly.
+const DENSE_MATRIX_LIMIT: usize = 64;
+
+/// All stones reachable from a set of initial stones, with an edge from
+/// every stone to each stone it turns into after one blink. Since the set
+/// is closed under blinking, counts after any number of blinks only depend
+/// on this graph.
+#[derive(Debug, Clone)]
+pub struct TransitionGraph {
+    stones: Vec<Stone>,
+    index: HashMap<Stone, usize>,
+    // A stone splitting into two equal halves has the same child twice
+    children: Vec<Vec<usize>>,
+}
+
+impl TransitionGraph {
+    pub fn build(rules: &RuleSet, initial: &[Stone]) -> Result<Self, BlinkError> {
+        let mut graph = Self {
+            stones: vec![],
+            index: HashMap::new(),
+            children: vec![],
+        };
+
+        let mut queue = initial
+            .iter()
+            .map(|&stone| graph.node(stone))
+            .collect::<VecDeque<_>>();
+        let mut visited = vec![];
+
+        while let Some(node) = queue.pop_front() {
+            visited.resize(graph.stones.len(), false);
+            if visited[node] {
+                continue;
+            }
+            visited[node] = true;
+
+            let children = match rules.blink(graph.stones[node])? {
+                Either::Left(s) => vec![graph.node(s)],
+                Either::Right((l, r)) => vec![graph.node(l), graph.node(r)],
+            };
+            queue.extend(children.iter().copied());
+            graph.children[node] = children;
+        }
+
+        Ok(graph)
+    }
+
+    fn node(&mut self, stone: Stone) -> usize {
+        *self.index.entry(stone).or_insert_with(|| {
+            self.stones.push(stone);
+            self.children.push(vec![]);
+            self.stones.len() - 1
+        })
+    }
+
+    /// The closed set of stones, sorted by engraved number.
+    pub fn closed_set(&self) -> Vec<Stone> {
+        let mut stones = self.stones.clone();
+        stones.sort_unstable();
+        stones
+    }
+
+    pub fn len(&self) -> usize {
+        self.stones.len()
+    }
+
+    /// Stone histogram over the graph's nodes for the initial stones.
+    fn initial_counts(&self, initial: &[Stone], modulus: u64) -> Vec<u64> {
+        let mut counts = vec![0; self.len()];
+        for stone in initial {
+            let node = self.index[stone];
+            counts[node] = (counts[node] + 1) % modulus;
+        }
+        counts
+    }
+
+    fn step(&self, counts: &[u64], modulus: u64) -> Vec<u64> {
+        let mut next = vec![0; counts.len()];
+        for (node, &count) in counts.iter().enumerate() {
+            for &child in self.children[node].iter() {
+                next[child] = (next[child] + count) % modulus;
+            }
+        }
+        next
+    }
+
+    /// Transition matrix, `m[i][j]` is the number of stones `j` a single
+    /// stone `i` turns into after one blink.
+    pub fn matrix(&self, modulus: u64) -> ModMatrix {
+        let mut m = ModMatrix::zeros(self.len(), modulus);
+        for (node, children) in self.children.iter().enumerate() {
+            for &child in children.iter() {
+                m.add(node, child, 1);
+            }
+        }
+        m
+    }
+
+    /// Stone count after `blinks` blinks modulo `modulus` by raising the
+    /// dense transition matrix to the power `blinks`. This is cubic in the
+    /// size of the closed set, so only suitable for small graphs.
+    pub fn count_mod_matrix(&self, initial: &[Stone], blinks: u64, modulus: u64) -> u64 {
+        let m = self.matrix(modulus).pow(blinks);
+        let counts = self.initial_counts(initial, modulus);
+
+        counts
+            .iter()
+            .enumerate()
+            .flat_map(|(i, &c)| (0..self.len()).map(move |j| (i, j, c)))
+            .fold(0, |acc, (i, j, c)| {
+                (acc + mul_mod(c, m.get(i, j), modulus)) % modulus
+            })
+    }
+
+    /// Stone count after `blinks` blinks modulo `modulus`. The recurrence
+    /// needs to divide modulo `modulus`, so a composite one falls back to
+    /// whichever of simulating every blink and the matrix power is cheaper.
+    pub fn count_mod(&self, initial: &[Stone], blinks: u64, modulus: u64) -> u64 {
+        if self.len() <= DENSE_MATRIX_LIMIT {
+            self.count_mod_matrix(initial, blinks, modulus)
+        } else if is_prime(modulus) {
+            self.count_mod_recurrence(initial, blinks, modulus)
+        } else if blinks / (self.len() as u64).pow(2) < blinks.max(1).ilog2() as u64 {
+            self.count_mod_steps(initial, blinks, modulus)
+        } else {
+            self.count_mod_matrix(initial, blinks, modulus)
+        }
+    }
+
+    /// Stone count after `blinks` blinks modulo `modulus`, one blink at a
+    /// time.
+    fn count_mod_steps(&self, initial: &[Stone], blinks: u64, modulus: u64) -> u64 {
+        let mut counts = self.initial_counts(initial, modulus);
+        for _ in 0..blinks {
+            counts = self.step(&counts, modulus);
+        }
+        counts.iter().fold(0, |acc, &c| (acc + c) % modulus)
+    }
+
+    /// The count sequence satisfies the linear recurrence given by the
+    /// minimal polynomial of the transition matrix (Cayley-Hamilton), which
+    /// has degree at most the size of the closed set. The recurrence is
+    /// recovered with Berlekamp-Massey from the first terms, and the matrix
+    /// power is then evaluated as `x^blinks` modulo that polynomial. This
+    /// is quadratic rather than cubic in the graph size per squaring, which
+    /// makes blink counts in the millions feasible on the puzzle's graph.
+    fn count_mod_recurrence(&self, initial: &[Stone], blinks: u64, modulus: u64) -> u64 {
+        let mut counts = self.initial_counts(initial, modulus);
+        let mut sequence = Vec::with_capacity(2 * self.len() + 1);
+        for _ in 0..=2 * self.len() {
+            if sequence.len() as u64 > blinks {
+                return sequence[blinks as usize];
+            }
+            sequence.push(counts.iter().fold(0, |acc, &c| (acc + c) % modulus));
+            counts = self.step(&counts, modulus);
+        }
+
+        let recurrence = berlekamp_massey(&sequence, modulus);
+        linear_recurrence_nth(&recurrence, &sequence, blinks, modulus)
+    }
+}
+
+fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    ((a as u128 * b as u128) % modulus as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, modulus: u64) -> u64 {
+    let mut result = 1 % modulus;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Deterministic Miller-Rabin, these bases cover every `u64`.
+fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    if let Some(&base) = BASES.iter().find(|&&base| n.is_multiple_of(base)) {
+        return n == base;
+    }
+
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    BASES.iter().all(|&base| {
+        let mut x = pow_mod(base, d, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        (1..s).any(|_| {
+            x = mul_mod(x, x, n);
+            x == n - 1
+        })
+    })
+}
+
+/// Shortest recurrence `s[n] = sum(c[i] * s[n - 1 - i])` generating
+/// `sequence`, requires a prime modulus.
+fn berlekamp_massey(sequence: &[u64], modulus: u64) -> Vec<u64> {
+    let n = sequence.len();
+    // Connection polynomials, current and at the last length change
+    let mut current = vec![0u64; n + 1];
+    let mut previous = vec![0u64; n + 1];
+    current[0] = 1;
+    previous[0] = 1;
+
+    let mut len = 0;
+    let mut since_change = 0;
+    let mut previous_delta = 1;
+
+    for i in 0..n {
+        since_change += 1;
+        let delta = (1..=len).fold(sequence[i], |acc, j| {
+            (acc + mul_mod(current[j], sequence[i - j], modulus)) % modulus
+        });
+        if delta == 0 {
+            continue;
+        }
+
+        let snapshot = current.clone();
+        let coef = mul_mod(
+            delta,
+            pow_mod(previous_delta, modulus - 2, modulus),
+            modulus,
+        );
+        for j in since_change..=n {
+            let sub = mul_mod(coef, previous[j - since_change], modulus);
+            current[j] = (current[j] + modulus - sub) % modulus;
+        }
+
+        if 2 * len > i {
+            continue;
+        }
+        len = i + 1 - len;
+        previous = snapshot;
+        previous_delta = delta;
+        since_change = 0;
+    }
+
+    current[1..=len]
+        .iter()
+        .map(|&c| (modulus - c) % modulus)
+        .collect()
+}
+
+/// Multiplies two polynomials modulo the characteristic polynomial of the
+/// recurrence, `x^d = sum(c[i] * x^(d - 1 - i))`.
+fn poly_mul_mod(a: &[u64], b: &[u64], recurrence: &[u64], modulus: u64) -> Vec<u64> {
+    let d = recurrence.len();
+    let mut product = vec![0u64; 2 * d];
+    for (i, &x) in a.iter().enumerate().filter(|(_, &x)| x != 0) {
+        for (j, &y) in b.iter().enumerate() {
+            product[i + j] = (product[i + j] + mul_mod(x, y, modulus)) % modulus;
+        }
+    }
+
+    for k in (d..2 * d).rev() {
+        let top = product[k];
+        if top == 0 {
+            continue;
+        }
+        for (i, &c) in recurrence.iter().enumerate() {
+            product[k - 1 - i] = (product[k - 1 - i] + mul_mod(top, c, modulus)) % modulus;
+        }
+    }
+
+    product.truncate(d);
+    product
+}
+
+/// `n`-th term of the sequence generated by `recurrence` from the given
+/// initial terms, evaluated as `x^n` modulo the characteristic polynomial.
+fn linear_recurrence_nth(recurrence: &[u64], initial: &[u64], n: u64, modulus: u64) -> u64 {
+    let d = recurrence.len();
+    if d == 0 {
+        return 0;
+    }
+
+    let mut result = vec![0u64; d];
+    result[0] = 1;
+    let mut base = vec![0u64; d];
+    if d == 1 {
+        base[0] = recurrence[0];
+    } else {
+        base[1] = 1;
+    }
+
+    let mut exp = n;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = poly_mul_mod(&result, &base, recurrence, modulus);
+        }
+        base = poly_mul_mod(&base, &base, recurrence, modulus);
+        exp >>= 1;
+    }
+
+    result
+        .iter()
+        .zip(initial.iter())
+        .fold(0, |acc, (&r, &s)| (acc + mul_mod(r, s, modulus)) % modulus)
+}
+
+/// Dense square matrix with entries modulo `modulus`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModMatrix {
+    data: Vec<u64>,
+    size: usize,
+    modulus: u64,
+}
+
+impl ModMatrix {
+    pub fn zeros(size: usize, modulus: u64) -> Self {
+        Self {
+            data: vec![0; size * size],
+            size,
+            modulus,
+        }
+    }
+
+    pub fn identity(size: usize, modulus: u64) -> Self {
+        let mut m = Self::zeros(size, modulus);
+        for i in 0..size {
+            m.add(i, i, 1);
+        }
+        m
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> u64 {
+        self.data[row * self.size + col]
+    }
+
+    pub fn add(&mut self, row: usize, col: usize, value: u64) {
+        let entry = &mut self.data[row * self.size + col];
+        *entry = (*entry + value) % self.modulus;
+    }
+
+    pub fn mul(&self, other: &ModMatrix) -> ModMatrix {
+        let mut product = ModMatrix::zeros(self.size, self.modulus);
+        for i in 0..self.size {
+            for k in 0..self.size {
+                let a = self.get(i, k);
+                if a == 0 {
+                    continue;
+                }
+                for j in 0..self.size {
+                    product.add(i, j, mul_mod(a, other.get(k, j), self.modulus));
+                }
+            }
+        }
+        product
+    }
+
+    pub fn pow(&self, mut exp: u64) -> ModMatrix {
+        let mut result = ModMatrix::identity(self.size, self.modulus);
+        let mut base = self.clone();
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.mul(&base);
+            }
+            base = base.mul(&base);
+            exp >>= 1;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pebbles::StoneCounter;
+
+    const PRIME: u64 = 1_000_000_007;
+
+    #[test]
+    fn closed_set_is_closed() {
+        let rules = RuleSet::default();
+        let graph = TransitionGraph::build(&rules, &[125, 17]).unwrap();
+        let closed_set = graph.closed_set();
+
+        for &stone in closed_set.iter() {
+            match rules.blink(stone).unwrap() {
+                Either::Left(s) => assert!(closed_set.binary_search(&s).is_ok()),
+                Either::Right((l, r)) => {
+                    assert!(closed_set.binary_search(&l).is_ok());
+                    assert!(closed_set.binary_search(&r).is_ok());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn count_mod_matches_memoised_count() {
+        let stones = [125, 17];
+        let graph = TransitionGraph::build(&RuleSet::default(), &stones).unwrap();
+        let mut counter = StoneCounter::new(RuleSet::default());
+
+        for blinks in [0, 1, 6, 25, 75, 200, 300] {
+            let expected = counter
+                .count_all(&stones, blinks)
+                .unwrap()
+                .rem_u32(PRIME as u32) as u64;
+            assert_eq!(graph.count_mod(&stones, blinks as u64, PRIME), expected);
+        }
+    }
+
+    #[test]
+    fn primality() {
+        let primes = [
+            2,
+            3,
+            37,
+            41,
+            1_000_000_007,
+            998_244_353,
+            18446744073709551557,
+        ];
+        assert!(primes.into_iter().all(is_prime));
+
+        // Carmichael numbers and a product of two large primes
+        let composites = [
+            0,
+            1,
+            4,
+            561,
+            1_000_000_000,
+            3215031751,
+            1_000_000_007 * 998_244_353,
+        ];
+        assert!(!composites.into_iter().any(is_prime));
+    }
+
+    #[test]
+    fn composite_modulus() {
+        let stones = [125, 17];
+        let graph = TransitionGraph::build(&RuleSet::default(), &stones).unwrap();
+        assert!(graph.len() > DENSE_MATRIX_LIMIT);
+
+        // Far enough that the recurrence would be needed
+        let (modulus, blinks) = (1 << 32, 3 * graph.len() as u64);
+        let expected = graph.count_mod_steps(&stones, blinks, modulus);
+        assert_eq!(graph.count_mod(&stones, blinks, modulus), expected);
+        assert_eq!(graph.count_mod_matrix(&stones, blinks, modulus), expected);
+    }
+
+    #[test]
+    fn count_mod_matches_matrix_power() {
+        let stones = [0];
+        let graph = TransitionGraph::build(&RuleSet::default(), &stones).unwrap();
+
+        for blinks in [10, 100, 1_000_000] {
+            assert_eq!(
+                graph.count_mod_recurrence(&stones, blinks, PRIME),
+                graph.count_mod_matrix(&stones, blinks, PRIME)
+            );
+        }
+    }
+}
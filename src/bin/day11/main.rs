@@ -1,93 +1,61 @@
-use std::collections::HashMap;
+mod graph;
+mod pebbles;
 
 use aoc_2024::input::read_lines;
-use itertools::Either;
-
-fn split_number_if_even(number: usize) -> Option<(usize, usize)> {
-    if number == 0 {
-        return None;
-    }
-    let n_log10 = number.ilog10() + 1;
-    if n_log10.rem_euclid(2) != 0 {
-        None
-    } else {
-        let divider = 10usize.pow(n_log10 / 2);
-
-        let left = number / divider;
-        let right = number - left * divider;
+use graph::TransitionGraph;
+use pebbles::{count_stones, RuleSet, Stone, StoneCounter};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let lines = read_lines("./inputs/day11/input.txt")?;
+    let input = lines.map_while(Result::ok).collect::<Vec<_>>();
+    assert!(input.len() == 1);
+
+    let stones = input[0]
+        .split(" ")
+        .map(|d| d.parse::<Stone>())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    println!("Stones after 25 blinks: {}", count_stones(&stones, 25)?);
+
+    let mut counter = StoneCounter::new(RuleSet::default());
+    println!(
+        "Stones after 75 blinks: {}",
+        counter.count_all(&stones, 75)?
+    );
+
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if(|arg| arg == "--analyse").is_some() {
+        let blinks = args.next().map(|n| n.parse()).transpose()?.unwrap_or(75);
+        let modulus = args
+            .next()
+            .map(|n| n.parse())
+            .transpose()?
+            .unwrap_or(1_000_000_007);
+        if modulus == 0 {
+            return Err("the modulus must be positive".into());
+        }
 
-        Some((left, right))
-    }
-}
+        let graph = TransitionGraph::build(counter.rules(), &stones)?;
+        println!("Closed set of stones: {:?}", graph.closed_set());
+        println!("Size of closed set: {}", graph.len());
+        println!(
+            "Stones after {} blinks mod {}: {}",
+            blinks,
+            modulus,
+            graph.count_mod(&stones, blinks, modulus)
+        );
 
-fn blink(stone: usize) -> Either<usize, (usize, usize)> {
-    if let Some((left, right)) = split_number_if_even(stone) {
-        Either::Right((left, right))
-    } else if stone == 0 {
-        Either::Left(1)
-    } else {
-        Either::Left(stone * 2024)
+        return Ok(());
     }
-}
-
-fn insert_or_increment(map: &mut HashMap<usize, usize>, key: &usize, inc: usize) {
-    if let Some(cnt) = map.get_mut(key) {
-        *cnt += inc;
-    } else {
-        map.insert(*key, inc);
-    }
-}
-
-fn main() {
-    if let Ok(lines) = read_lines("./inputs/day11/input.txt") {
-        // Parsing
-        let input = lines.flatten().collect::<Vec<_>>();
-        assert!(input.len() == 1);
-
-        let mut cache: HashMap<usize, Either<usize, (usize, usize)>> = HashMap::new();
-
-        let mut stones = input[0]
-            .split(" ")
-            .map(|d| (d.parse::<usize>().unwrap(), 1usize))
-            .collect::<HashMap<usize, usize>>();
-
-        let mut new_stones: HashMap<usize, usize> = HashMap::new();
-
-        for _ in 0..100 {
-            for (stone, num) in stones.iter() {
-                let res = if let Some(res) = cache.get(stone) {
-                    res
-                } else {
-                    let res = blink(*stone);
-                    cache.insert(*stone, res);
-                    cache.get(&stone).unwrap()
-                };
-                match res {
-                    Either::Left(s) => {
-                        insert_or_increment(&mut new_stones, s, *num);
-                    }
-                    Either::Right((l, r)) => {
-                        insert_or_increment(&mut new_stones, l, *num);
-                        insert_or_increment(&mut new_stones, r, *num);
-                    }
-                }
-            }
-
-            stones.iter_mut().for_each(|(_, cnt)| *cnt = 0);
-            new_stones.iter_mut().for_each(|(stone, cnt)| {
-                insert_or_increment(&mut stones, stone, *cnt);
-                *cnt = 0;
-            });
-            println!("Number of unique stones seen: {}", stones.len());
-        }
-
-        let mut keys = stones.keys().cloned().collect::<Vec<_>>();
-        keys.sort();
-        println!("Stones: {:?}", keys);
 
+    if let Some(blinks) = args.next() {
+        let blinks = blinks.parse::<usize>()?;
         println!(
-            "Number of stones: {}",
-            stones.iter().map(|(_, cnt)| *cnt).sum::<usize>()
+            "Stones after {} blinks: {}",
+            blinks,
+            counter.count_all(&stones, blinks)?
         );
     }
+
+    Ok(())
 }
@@ -0,0 +1,226 @@
+use std::collections::{HashMap, HashSet};
+
+use aoc_2024::bigint::BigUint;
+use itertools::Either;
+
+pub type Stone = u64;
+
+/// A single blink rule. Rules are tried in order and the first one that
+/// applies to a stone decides what it turns into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// A stone engraved with exactly `from` is replaced by `to`.
+    Replace { from: Stone, to: Stone },
+    /// A stone with an even number of digits is split into its two halves.
+    SplitEvenDigits,
+    /// The engraved number is multiplied by the factor.
+    Multiply(Stone),
+}
+
+impl Rule {
+    /// What the stone turns into, or `None` if the rule doesn't apply.
+    pub fn apply(&self, stone: Stone) -> Result<Option<Blinked>, BlinkError> {
+        match *self {
+            Rule::Replace { from, to } => Ok((stone == from).then_some(Either::Left(to))),
+            Rule::SplitEvenDigits => Ok(split_number_if_even(stone).map(Either::Right)),
+            Rule::Multiply(factor) => stone
+                .checked_mul(factor)
+                .map(|s| Some(Either::Left(s)))
+                .ok_or(BlinkError::Overflow { stone, factor }),
+        }
+    }
+}
+
+/// A stone after one blink, either one stone or two.
+pub type Blinked = Either<Stone, (Stone, Stone)>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlinkError {
+    #[error("no rule applies to stone {0}")]
+    NoRule(Stone),
+    #[error("stone {stone} times {factor} overflows u64")]
+    Overflow { stone: Stone, factor: Stone },
+    #[error("the stone count doesn't fit in a u128")]
+    CountOverflow,
+}
+
+fn split_number_if_even(number: Stone) -> Option<(Stone, Stone)> {
+    if number == 0 {
+        return None;
+    }
+    let n_log10 = number.ilog10() + 1;
+    if n_log10.rem_euclid(2) != 0 {
+        None
+    } else {
+        let divider = (10 as Stone).pow(n_log10 / 2);
+
+        let left = number / divider;
+        let right = number - left * divider;
+
+        Some((left, right))
+    }
+}
+
+/// The puzzle's rules: 0 becomes 1, even digit counts split, anything else
+/// is multiplied by 2024.
+pub const PUZZLE_RULES: [Rule; 3] = [
+    Rule::Replace { from: 0, to: 1 },
+    Rule::SplitEvenDigits,
+    Rule::Multiply(2024),
+];
+
+#[derive(Debug, Clone)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self::new(PUZZLE_RULES.to_vec())
+    }
+}
+
+impl RuleSet {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    pub fn blink(&self, stone: Stone) -> Result<Blinked, BlinkError> {
+        for rule in self.rules.iter() {
+            if let Some(blinked) = rule.apply(stone)? {
+                return Ok(blinked);
+            }
+        }
+
+        Err(BlinkError::NoRule(stone))
+    }
+}
+
+/// Counts stones with memoisation keyed by `(stone, remaining_blinks)`.
+/// The memo is filled bottom-up, from the last blink back to the first, so
+/// deep counts don't recurse. Counts are arbitrary precision, so any number
+/// of blinks works.
+#[derive(Debug, Clone, Default)]
+pub struct StoneCounter {
+    rules: RuleSet,
+    memo: HashMap<(Stone, usize), BigUint>,
+}
+
+impl StoneCounter {
+    pub fn new(rules: RuleSet) -> Self {
+        Self {
+            rules,
+            memo: HashMap::new(),
+        }
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    pub fn count_all(&mut self, stones: &[Stone], blinks: usize) -> Result<BigUint, BlinkError> {
+        // Stones after exactly `depth` blinks that aren't counted yet
+        let mut layers = vec![stones.iter().copied().collect::<HashSet<_>>()];
+        for depth in 0..blinks {
+            let mut next = HashSet::new();
+            for &stone in layers[depth].iter() {
+                if self.memo.contains_key(&(stone, blinks - depth)) {
+                    continue;
+                }
+                match self.rules.blink(stone)? {
+                    Either::Left(s) => {
+                        next.insert(s);
+                    }
+                    Either::Right((l, r)) => next.extend([l, r]),
+                }
+            }
+            layers.push(next);
+        }
+
+        for (depth, layer) in layers.iter().enumerate().rev() {
+            let remaining = blinks - depth;
+            for &stone in layer.iter() {
+                if self.memo.contains_key(&(stone, remaining)) {
+                    continue;
+                }
+                let count = match remaining {
+                    0 => BigUint::from(1),
+                    _ => match self.rules.blink(stone)? {
+                        Either::Left(s) => self.memo[&(s, remaining - 1)].clone(),
+                        Either::Right((l, r)) => {
+                            &self.memo[&(l, remaining - 1)] + &self.memo[&(r, remaining - 1)]
+                        }
+                    },
+                };
+                self.memo.insert((stone, remaining), count);
+            }
+        }
+
+        Ok(stones.iter().fold(BigUint::zero(), |total, &stone| {
+            &total + &self.memo[&(stone, blinks)]
+        }))
+    }
+}
+
+/// Number of stones after `blinks` blinks using the puzzle's rules, or an
+/// error once that no longer fits in a `u128`.
+pub fn count_stones(initial: &[Stone], blinks: usize) -> Result<u128, BlinkError> {
+    StoneCounter::default()
+        .count_all(initial, blinks)?
+        .to_u128()
+        .ok_or(BlinkError::CountOverflow)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn example_counts() {
+        assert_eq!(count_stones(&[125, 17], 6).unwrap(), 22);
+        assert_eq!(count_stones(&[125, 17], 25).unwrap(), 55312);
+    }
+
+    #[test]
+    fn custom_rules() {
+        // Without the split rule every stone stays a single stone
+        let mut counter = StoneCounter::new(RuleSet::new(vec![
+            Rule::Replace { from: 0, to: 1 },
+            Rule::Multiply(1),
+        ]));
+        assert_eq!(
+            counter.count_all(&[0, 7, 1234], 500).unwrap(),
+            BigUint::from(3)
+        );
+        // Deep enough to overflow the stack if counted recursively
+        assert_eq!(
+            counter.count_all(&[0, 7, 1234], 100_000).unwrap(),
+            BigUint::from(3)
+        );
+
+        // Nothing handles single digits here
+        let mut counter = StoneCounter::new(RuleSet::new(vec![Rule::SplitEvenDigits]));
+        assert!(matches!(
+            counter.count_all(&[1234], 3),
+            Err(BlinkError::NoRule(_))
+        ));
+
+        let mut counter = StoneCounter::new(RuleSet::new(vec![Rule::Multiply(1 << 32)]));
+        assert!(matches!(
+            counter.count_all(&[3], 2),
+            Err(BlinkError::Overflow { .. })
+        ));
+    }
+
+    #[test]
+    fn hundreds_of_blinks() {
+        assert!(count_stones(&[125, 17], 150).unwrap() > u64::MAX as u128);
+        assert!(matches!(
+            count_stones(&[125, 17], 300),
+            Err(BlinkError::CountOverflow)
+        ));
+
+        let count = StoneCounter::default().count_all(&[125, 17], 300).unwrap();
+        assert!(count.to_u128().is_none());
+    }
+}
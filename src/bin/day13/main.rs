@@ -1,6 +1,9 @@
+mod solver;
+
 use aoc_2024::input::read_lines;
 use itertools::Itertools;
 use regex::Regex;
+use solver::{solve, Button, Solution};
 use std::sync::LazyLock;
 
 #[derive(Debug)]
@@ -10,97 +13,201 @@ struct ClawMachine {
     pub prize: (i64, i64),
 }
 
-fn parse_button(line: &str) -> Option<(i64, i64)> {
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+#[error("line {line}: {kind}, got {text:?}")]
+pub struct ParseError {
+    /// One-based line number.
+    pub line: usize,
+    pub text: String,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ParseErrorKind {
+    #[error("expected \"Button {0}: X+<x>, Y+<y>\"")]
+    ExpectedButton(char),
+    #[error("expected Button {expected}")]
+    WrongLabel { expected: char, found: char },
+    #[error("expected \"Prize: X=<x>, Y=<y>\"")]
+    ExpectedPrize,
+    #[error("number out of range")]
+    OutOfRange,
+    #[error("input ends in the middle of a machine")]
+    UnexpectedEnd,
+}
+
+/// Parses a button line, which must be labelled `expected`.
+fn parse_button(line: &str, expected: char) -> Result<(i64, i64), ParseErrorKind> {
     static RE: LazyLock<Regex> =
-        LazyLock::new(|| Regex::new(r"^Button [A|B]: X\+(?<X>\d+), Y\+(?<Y>\d+)$").unwrap());
+        LazyLock::new(|| Regex::new(r"^Button (?<L>[AB]): X\+(?<X>\d+), Y\+(?<Y>\d+)$").unwrap());
 
-    RE.captures(line)
+    let (_, [label, x, y]) = RE
+        .captures(line)
         .map(|caps| caps.extract())
-        .and_then(|(_, [x, y])| match (x.parse::<i64>(), y.parse::<i64>()) {
-            (Ok(x), Ok(y)) => Some((x, y)),
-            _ => None,
-        })
+        .ok_or(ParseErrorKind::ExpectedButton(expected))?;
+
+    let found = label.chars().next().unwrap();
+    if found != expected {
+        return Err(ParseErrorKind::WrongLabel { expected, found });
+    }
+
+    parse_pair(x, y)
 }
 
-fn parse_prize(line: &str) -> Option<(i64, i64)> {
+fn parse_prize(line: &str) -> Result<(i64, i64), ParseErrorKind> {
     static RE: LazyLock<Regex> =
         LazyLock::new(|| Regex::new(r"^Prize: X=(?<X>\d+), Y=(?<Y>\d+)$").unwrap());
 
-    RE.captures(line)
+    let (_, [x, y]) = RE
+        .captures(line)
         .map(|caps| caps.extract())
-        .and_then(|(_, [x, y])| match (x.parse::<i64>(), y.parse::<i64>()) {
-            (Ok(x), Ok(y)) => Some((x, y)),
-            _ => None,
-        })
+        .ok_or(ParseErrorKind::ExpectedPrize)?;
+
+    parse_pair(x, y)
 }
 
-fn solve_system(a: (i64, i64), b: (i64, i64), c: (i64, i64)) -> Option<(i64, i64)> {
-    if (a.1 * c.0 - a.0 * c.1).rem_euclid(a.1 * b.0 - a.0 * b.1) != 0 {
-        return None;
+fn parse_pair(x: &str, y: &str) -> Result<(i64, i64), ParseErrorKind> {
+    match (x.parse::<i64>(), y.parse::<i64>()) {
+        (Ok(x), Ok(y)) => Ok((x, y)),
+        _ => Err(ParseErrorKind::OutOfRange),
     }
-    let v = (a.1 * c.0 - a.0 * c.1) / (a.1 * b.0 - a.0 * b.1);
+}
 
-    if (c.1 - b.1 * v).rem_euclid(a.1) != 0 {
-        return None;
+fn at_line((line, text): &(usize, String), kind: ParseErrorKind) -> ParseError {
+    ParseError {
+        line: *line,
+        text: text.clone(),
+        kind,
     }
+}
 
-    let u = (c.1 - b.1 * v) / a.1;
+/// Parses machines of three lines each: Button A, Button B and Prize.
+/// Blank lines between machines or at the end and CRLF line endings are
+/// ignored.
+fn parse_machines<S: AsRef<str>>(
+    lines: impl IntoIterator<Item = S>,
+) -> Result<Vec<ClawMachine>, ParseError> {
+    let lines = lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.as_ref().trim_end_matches('\r').to_string()))
+        .filter(|(_, line)| !line.trim().is_empty())
+        .collect_vec();
 
-    Some((u, v))
+    lines
+        .chunks(3)
+        .map(|chunk| {
+            let line = |i: usize| {
+                chunk.get(i).ok_or_else(|| ParseError {
+                    line: chunk.last().unwrap().0 + 1,
+                    text: String::new(),
+                    kind: ParseErrorKind::UnexpectedEnd,
+                })
+            };
+            let (a, b, prize) = (line(0)?, line(1)?, line(2)?);
+            Ok(ClawMachine {
+                btn_a: parse_button(&a.1, 'A').map_err(|kind| at_line(a, kind))?,
+                btn_b: parse_button(&b.1, 'B').map_err(|kind| at_line(b, kind))?,
+                prize: parse_prize(&prize.1).map_err(|kind| at_line(prize, kind))?,
+            })
+        })
+        .collect()
 }
 
-fn main() {
+impl ClawMachine {
+    /// The machine's buttons: A costs 3 tokens, B costs 1.
+    fn buttons(&self, limit: Option<u64>) -> [Button; 2] {
+        [(self.btn_a, 3), (self.btn_b, 1)].map(|(delta, cost)| Button { delta, cost, limit })
+    }
+}
+
+/// Tokens to win every prize that can be won.
+fn total_cost(machines: &[ClawMachine], offset: i64, limit: Option<u64>) -> u64 {
+    machines
+        .iter()
+        .map(|cm| {
+            let target = (cm.prize.0 + offset, cm.prize.1 + offset);
+            // Two buttons always leave nothing to enumerate
+            solve(&cm.buttons(limit), target).unwrap()
+        })
+        .map(|solution| match solution {
+            Solution::Cheapest { cost, .. } => cost,
+            Solution::Unreachable => 0,
+        })
+        .sum()
+}
+
+fn main() -> Result<(), ParseError> {
     if let Ok(lines) = read_lines("./inputs/day13/input.txt") {
         // Parsing
-        let input = lines
-            .flatten()
-            .filter(|s| !s.is_empty())
-            .chunks(3)
-            .into_iter()
-            .map(|mut chunk| ClawMachine {
-                btn_a: chunk
-                    .next()
-                    .and_then(|line| parse_button(&line))
-                    .expect("Failed parsing Button A"),
-                btn_b: chunk
-                    .next()
-                    .and_then(|line| parse_button(&line))
-                    .expect("Failed parsing Button B"),
-                prize: chunk
-                    .next()
-                    .and_then(|line| parse_prize(&line))
-                    .expect("Failed parsing Prize"),
-            })
-            .collect_vec();
-
-        let star1 = input
-            .iter()
-            .map(|cm| solve_system(cm.btn_a, cm.btn_b, cm.prize))
-            .flatten()
-            .inspect(|v| {
-                dbg!(v);
-            })
-            .map(|v| v.0 * 3 + v.1)
-            .sum::<i64>();
+        let input = parse_machines(lines.map_while(Result::ok))?;
+
+        let star1 = total_cost(&input, 0, Some(100));
 
         println!("Star 1 cost: {}", star1);
 
-        let star2 = input
-            .iter()
-            .map(|cm| {
-                solve_system(
-                    cm.btn_a,
-                    cm.btn_b,
-                    (cm.prize.0 + 10000000000000, cm.prize.1 + 10000000000000),
-                )
-            })
-            .flatten()
-            .inspect(|v| {
-                dbg!(v);
-            })
-            .map(|v| v.0 * 3 + v.1)
-            .sum::<i64>();
+        let star2 = total_cost(&input, 10000000000000, None);
 
         println!("Star 2 cost: {}", star2);
     }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_with_crlf_and_blank_lines() {
+        let input = "Button A: X+94, Y+34\r\nButton B: X+22, Y+67\r\nPrize: X=8400, Y=5400\r\n\r\n\r\nButton A: X+26, Y+66\r\nButton B: X+67, Y+21\r\nPrize: X=12748, Y=12176\r\n\r\n\r\n";
+        let machines = parse_machines(input.split('\n')).unwrap();
+
+        assert_eq!(machines.len(), 2);
+        assert_eq!(machines[1].btn_b, (67, 21));
+        assert_eq!(machines[1].prize, (12748, 12176));
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_line() {
+        let machine = |a: &str, b: &str| {
+            parse_machines(["", a, b, "Prize: X=1, Y=2"])
+                .map(|_| ())
+                .unwrap_err()
+        };
+
+        assert_eq!(
+            machine("Button B: X+1, Y+2", "Button A: X+3, Y+4"),
+            ParseError {
+                line: 2,
+                text: "Button B: X+1, Y+2".into(),
+                kind: ParseErrorKind::WrongLabel {
+                    expected: 'A',
+                    found: 'B'
+                },
+            }
+        );
+        assert_eq!(
+            machine("Button A: X+1, Y+2", "Button |: X+3, Y+4").kind,
+            ParseErrorKind::ExpectedButton('B')
+        );
+        assert_eq!(
+            machine(
+                "Button A: X+99999999999999999999, Y+2",
+                "Button B: X+3, Y+4"
+            )
+            .kind,
+            ParseErrorKind::OutOfRange
+        );
+
+        let truncated = parse_machines(["Button A: X+1, Y+2", "Button B: X+3, Y+4", ""]);
+        assert_eq!(
+            truncated.map(|_| ()).unwrap_err(),
+            ParseError {
+                line: 3,
+                text: String::new(),
+                kind: ParseErrorKind::UnexpectedEnd
+            }
+        );
+    }
 }
@@ -0,0 +1,353 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Button {
+    /// How far one press moves the claw.
+    pub delta: (i64, i64),
+    /// Tokens per press.
+    pub cost: u64,
+    /// Most presses allowed, if limited.
+    pub limit: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Solution {
+    /// The cheapest way to reach the prize, presses per button.
+    Cheapest {
+        presses: Vec<u64>,
+        cost: u64,
+    },
+    Unreachable,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SolveError {
+    #[error("presses of button {0} are unbounded, give it a limit")]
+    Unbounded(usize),
+}
+
+/// Finds the cheapest non-negative number of presses per button that moves
+/// the claw exactly onto `target`. The last two buttons are solved exactly
+/// for every combination of presses of the others, so only the others need
+/// to be bounded, by their limit or by the target.
+pub fn solve(buttons: &[Button], target: (i64, i64)) -> Result<Solution, SolveError> {
+    let free = buttons.len().saturating_sub(2);
+    let bounds = (0..free)
+        .map(|i| max_presses(buttons, i, target).ok_or(SolveError::Unbounded(i)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut best: Option<(u64, Vec<u64>)> = None;
+    let mut presses = vec![0; buttons.len()];
+    loop {
+        let (rest, pair) = buttons.split_at(free);
+        let moved = rest
+            .iter()
+            .zip(presses.iter())
+            .fold((0, 0), |acc, (b, &n)| {
+                (acc.0 + b.delta.0 * n as i64, acc.1 + b.delta.1 * n as i64)
+            });
+        let remaining = (target.0 - moved.0, target.1 - moved.1);
+
+        let solved = match pair {
+            [] => (remaining == (0, 0)).then_some(vec![]),
+            [a] => solve_single(a, remaining).map(|n| vec![n]),
+            [a, b] => solve_pair(a, b, remaining).map(|(n, m)| vec![n, m]),
+            _ => unreachable!(),
+        };
+        if let Some(solved) = solved {
+            presses[free..].copy_from_slice(&solved);
+            let cost = total_cost(buttons, &presses);
+            if best.as_ref().is_none_or(|(best, _)| cost < *best) {
+                best = Some((cost, presses.clone()));
+            }
+        }
+
+        // Next combination of the free buttons, like an odometer
+        let Some(i) = (0..free).find(|&i| presses[i] < bounds[i]) else {
+            break;
+        };
+        presses[i] += 1;
+        presses[..i].iter_mut().for_each(|n| *n = 0);
+    }
+
+    Ok(match best {
+        Some((cost, presses)) => Solution::Cheapest { presses, cost },
+        None => Solution::Unreachable,
+    })
+}
+
+fn total_cost(buttons: &[Button], presses: &[u64]) -> u64 {
+    buttons.iter().zip(presses).map(|(b, n)| b.cost * n).sum()
+}
+
+/// Upper bound on the presses of button `i`: its limit, or the target
+/// along an axis on which no button moves backwards.
+fn max_presses(buttons: &[Button], i: usize, target: (i64, i64)) -> Option<u64> {
+    let axis_bound = |axis: fn((i64, i64)) -> i64| {
+        let delta = axis(buttons[i].delta);
+        let forward = buttons.iter().all(|b| axis(b.delta) >= 0);
+        (delta > 0 && forward).then(|| axis(target).max(0) as u64 / delta as u64)
+    };
+
+    [buttons[i].limit, axis_bound(|d| d.0), axis_bound(|d| d.1)]
+        .into_iter()
+        .flatten()
+        .min()
+}
+
+fn within_limit(button: &Button, presses: i128) -> bool {
+    presses >= 0 && button.limit.is_none_or(|limit| presses <= limit as i128)
+}
+
+fn solve_single(a: &Button, target: (i64, i64)) -> Option<u64> {
+    let axis = if a.delta.0 != 0 { 0 } else { 1 };
+    let (delta, t) = [(a.delta.0, target.0), (a.delta.1, target.1)][axis];
+
+    let presses = match delta {
+        0 => 0,
+        _ if t % delta != 0 => return None,
+        _ => t / delta,
+    };
+    let reached = (a.delta.0 * presses, a.delta.1 * presses) == target;
+
+    (reached && within_limit(a, presses as i128)).then_some(presses as u64)
+}
+
+/// Cheapest presses of two buttons, exactly.
+fn solve_pair(a: &Button, b: &Button, target: (i64, i64)) -> Option<(u64, u64)> {
+    let (a_d, b_d) = (a.delta, b.delta);
+    let (a_d, b_d, t) = (
+        (a_d.0 as i128, a_d.1 as i128),
+        (b_d.0 as i128, b_d.1 as i128),
+        (target.0 as i128, target.1 as i128),
+    );
+
+    // Cramer's rule if the buttons move in different directions
+    let det = a_d.0 * b_d.1 - a_d.1 * b_d.0;
+    if det != 0 {
+        let n = t.0 * b_d.1 - t.1 * b_d.0;
+        let m = a_d.0 * t.1 - a_d.1 * t.0;
+        if n % det != 0 || m % det != 0 {
+            return None;
+        }
+        let (n, m) = (n / det, m / det);
+
+        return (within_limit(a, n) && within_limit(b, m)).then_some((n as u64, m as u64));
+    }
+
+    // Otherwise everything happens on one line: the target must be on it
+    // and the presses solve a single equation
+    let cross = |u: (i128, i128), v: (i128, i128)| u.0 * v.1 - u.1 * v.0;
+    if cross(a_d, t) != 0 || cross(b_d, t) != 0 {
+        return None;
+    }
+    let axis = if a_d.0 != 0 || b_d.0 != 0 { 0 } else { 1 };
+    let pick = |v: (i128, i128)| if axis == 0 { v.0 } else { v.1 };
+    // Neither button moves the claw, so only the start is reachable
+    if a_d == (0, 0) && b_d == (0, 0) {
+        return (t == (0, 0)).then_some((0, 0));
+    }
+    let (p, q, t) = (pick(a_d), pick(b_d), pick(t));
+
+    // All solutions of p * n + q * m = t are n0 + k * dn, m0 + k * dm
+    let (g, x, y) = extended_gcd(p, q);
+    if t % g != 0 {
+        return None;
+    }
+    let (n0, m0) = (x * (t / g), y * (t / g));
+    let (dn, dm) = (q / g, -p / g);
+
+    // Range of k that keeps both press counts within their bounds, `None`
+    // where unbounded
+    let (mut lo, mut hi) = (None::<i128>, None::<i128>);
+    let at_most = |hi: Option<i128>, bound: i128| Some(hi.map_or(bound, |hi| hi.min(bound)));
+    for (start, step, button) in [(n0, dn, a), (m0, dm, b)] {
+        let max = button.limit.map(|limit| limit as i128);
+        match step.signum() {
+            1 => {
+                lo = lo.max(Some(div_ceil(-start, step)));
+                if let Some(max) = max {
+                    hi = at_most(hi, div_floor(max - start, step));
+                }
+            }
+            -1 => {
+                hi = at_most(hi, div_floor(-start, step));
+                if let Some(max) = max {
+                    lo = lo.max(Some(div_ceil(max - start, step)));
+                }
+            }
+            _ => {
+                if !within_limit(button, start) {
+                    return None;
+                }
+            }
+        }
+    }
+    if let (Some(lo), Some(hi)) = (lo, hi) {
+        if lo > hi {
+            return None;
+        }
+    }
+
+    // The cost is linear in k, so the cheapest end of the range wins. Costs
+    // and presses are non-negative, so the cost can't fall forever.
+    let slope = a.cost as i128 * dn + b.cost as i128 * dm;
+    let k = match slope.signum() {
+        1 => lo?,
+        -1 => hi?,
+        _ => lo.or(hi)?,
+    };
+
+    Some(((n0 + k * dn) as u64, (m0 + k * dm) as u64))
+}
+
+/// `(g, x, y)` with `a * x + b * y = g`, where `g` is the positive gcd.
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a.abs(), a.signum(), 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a.rem_euclid(b));
+        (g, y, x - a.div_euclid(b) * y)
+    }
+}
+
+/// `ceil(a / b)` for a non-zero `b` of any sign.
+fn div_ceil(a: i128, b: i128) -> i128 {
+    -div_floor(-a, b)
+}
+
+/// `floor(a / b)` for a non-zero `b` of any sign.
+fn div_floor(a: i128, b: i128) -> i128 {
+    let (q, r) = (a / b, a % b);
+    if r != 0 && (r < 0) != (b < 0) {
+        q - 1
+    } else {
+        q
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn button(delta: (i64, i64), cost: u64) -> Button {
+        Button {
+            delta,
+            cost,
+            limit: None,
+        }
+    }
+
+    #[test]
+    fn two_buttons() {
+        let (a, b) = (button((94, 34), 3), button((22, 67), 1));
+        assert_eq!(
+            solve(&[a, b], (8400, 5400)).unwrap(),
+            Solution::Cheapest {
+                presses: vec![80, 40],
+                cost: 280
+            }
+        );
+        assert_eq!(
+            solve(&[a, b], (12748, 12176)).unwrap(),
+            Solution::Unreachable
+        );
+
+        // Reachable only with a negative number of presses
+        assert_eq!(
+            solve(&[button((2, 1), 1), button((1, 2), 1)], (0, 3)).unwrap(),
+            Solution::Unreachable
+        );
+
+        let limited = Button {
+            limit: Some(50),
+            ..a
+        };
+        assert_eq!(
+            solve(&[limited, b], (8400, 5400)).unwrap(),
+            Solution::Unreachable
+        );
+    }
+
+    #[test]
+    fn collinear_buttons() {
+        // Both move along the diagonal, the cheaper per step wins
+        let (a, b) = (button((3, 3), 4), button((1, 1), 1));
+        assert_eq!(
+            solve(&[a, b], (10, 10)).unwrap(),
+            Solution::Cheapest {
+                presses: vec![0, 10],
+                cost: 10
+            }
+        );
+        let b = Button {
+            limit: Some(4),
+            ..b
+        };
+        assert_eq!(
+            solve(&[a, b], (10, 10)).unwrap(),
+            Solution::Cheapest {
+                presses: vec![2, 4],
+                cost: 12
+            }
+        );
+        assert_eq!(
+            solve(&[button((2, 2), 1), button((4, 4), 1)], (5, 5)).unwrap(),
+            Solution::Unreachable
+        );
+    }
+
+    #[test]
+    fn more_buttons() {
+        let buttons = [
+            button((1, 0), 1),
+            button((0, 1), 1),
+            button((1, 1), 1),
+            button((5, 5), 3),
+        ];
+        assert_eq!(
+            solve(&buttons, (7, 6)).unwrap(),
+            Solution::Cheapest {
+                presses: vec![1, 0, 1, 1],
+                cost: 5
+            }
+        );
+
+        // Buttons that don't move the claw can't reach anything else
+        let still = button((0, 0), 1);
+        assert_eq!(
+            solve(&[still, still], (5, 0)).unwrap(),
+            Solution::Unreachable
+        );
+        assert_eq!(
+            solve(&[still, still], (0, 5)).unwrap(),
+            Solution::Unreachable
+        );
+        assert_eq!(
+            solve(&[still, still], (0, 0)).unwrap(),
+            Solution::Cheapest {
+                presses: vec![0, 0],
+                cost: 0
+            }
+        );
+        let limited = Button {
+            limit: Some(5),
+            ..button((3, -1), 1)
+        };
+        assert_eq!(
+            solve(&[limited, still, still], (9, -1)).unwrap(),
+            Solution::Unreachable
+        );
+        assert_eq!(
+            solve(&[limited, still, still], (9, -3)).unwrap(),
+            Solution::Cheapest {
+                presses: vec![3, 0, 0],
+                cost: 3
+            }
+        );
+
+        let backwards = [button((-1, 0), 1), button((1, 0), 1), button((0, 1), 1)];
+        assert!(matches!(
+            solve(&backwards, (1, 1)),
+            Err(SolveError::Unbounded(0))
+        ));
+    }
+}
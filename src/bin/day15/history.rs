@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+
+use aoc_2024::utils::{Coord, Direction, Grid};
+
+use crate::{push::Warehouse, Entity};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellChange {
+    pub pos: Coord,
+    pub before: Entity,
+    pub after: Entity,
+}
+
+#[derive(Debug, Clone)]
+pub struct MoveRecord {
+    pub dir: Direction,
+    /// Empty if the robot was blocked.
+    pub changes: Vec<CellChange>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum InvariantError {
+    #[error("expected exactly one robot, found {0}")]
+    RobotCount(usize),
+    #[error("wide box half at {0:?} has no matching half")]
+    UnmatchedWideBox(Coord),
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("move {index} ({dir:?}) broke an invariant: {error}")]
+pub struct Violation {
+    /// Zero-based index of the move in the move list.
+    pub index: usize,
+    pub dir: Direction,
+    pub error: InvariantError,
+}
+
+/// Every move the robot made, with the cells it changed, and a cursor that
+/// can step forwards and backwards through them.
+pub struct History {
+    grid: Grid<Entity>,
+    log: Vec<MoveRecord>,
+    cursor: usize,
+    robots: usize,
+    violation: Option<Violation>,
+}
+
+impl History {
+    /// Runs all moves on the warehouse, recording them and checking the
+    /// invariants after every move. The cursor ends up after the last move.
+    pub fn record(warehouse: &mut Warehouse, moves: impl IntoIterator<Item = Direction>) -> Self {
+        let grid = warehouse.to_grid();
+        let robots = grid.iter().filter(|e| **e == Entity::Robot).count();
+        let mut history = Self {
+            grid,
+            log: vec![],
+            cursor: 0,
+            robots,
+            violation: None,
+        };
+
+        for dir in moves {
+            let changes = record_move(warehouse, dir);
+            history.log.push(MoveRecord { dir, changes });
+            history.step_forward();
+
+            if history.violation.is_none() {
+                if let Err(error) = history.check() {
+                    history.violation = Some(Violation {
+                        index: history.cursor - 1,
+                        dir,
+                        error,
+                    });
+                }
+            }
+        }
+
+        history
+    }
+
+    pub fn grid(&self) -> &Grid<Entity> {
+        &self.grid
+    }
+
+    pub fn len(&self) -> usize {
+        self.log.len()
+    }
+
+    /// Number of moves applied to `grid`.
+    pub fn position(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn first_violation(&self) -> Option<&Violation> {
+        self.violation.as_ref()
+    }
+
+    pub fn step_forward(&mut self) -> Option<&MoveRecord> {
+        let record = self.log.get(self.cursor)?;
+        for change in record.changes.iter() {
+            apply(&mut self.grid, &mut self.robots, change.pos, change.after);
+        }
+        self.cursor += 1;
+
+        self.log.get(self.cursor - 1)
+    }
+
+    pub fn step_back(&mut self) -> Option<&MoveRecord> {
+        self.cursor = self.cursor.checked_sub(1)?;
+        let record = &self.log[self.cursor];
+        for change in record.changes.iter() {
+            apply(&mut self.grid, &mut self.robots, change.pos, change.before);
+        }
+
+        Some(record)
+    }
+
+    /// Checks the invariants around the cells changed by the last applied
+    /// move. Cells that did not change can't have started violating them.
+    fn check(&self) -> Result<(), InvariantError> {
+        if self.robots != 1 {
+            return Err(InvariantError::RobotCount(self.robots));
+        }
+
+        let Some(record) = self.cursor.checked_sub(1).map(|i| &self.log[i]) else {
+            return Ok(());
+        };
+        for change in record.changes.iter() {
+            let pos = change.pos;
+            let neighbours = [
+                Some(pos),
+                Direction::Left.step(pos, self.grid.size()),
+                Direction::Right.step(pos, self.grid.size()),
+            ];
+            for pos in neighbours.into_iter().flatten() {
+                // Wide box tiles need a left part to their left and a
+                // right part to their right
+                let (left, right) = match self.grid.get(pos) {
+                    Entity::WideBoxLeft => (None, Some(Direction::Right)),
+                    Entity::WideBoxMiddle => (Some(Direction::Left), Some(Direction::Right)),
+                    Entity::WideBoxRight => (Some(Direction::Left), None),
+                    _ => continue,
+                };
+                let joined = |dir: Option<Direction>, partners: [Entity; 2]| {
+                    dir.is_none_or(|dir| {
+                        dir.step(pos, self.grid.size())
+                            .is_some_and(|p| partners.contains(self.grid.get(p)))
+                    })
+                };
+                if !joined(left, [Entity::WideBoxLeft, Entity::WideBoxMiddle])
+                    || !joined(right, [Entity::WideBoxMiddle, Entity::WideBoxRight])
+                {
+                    return Err(InvariantError::UnmatchedWideBox(pos));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn apply(grid: &mut Grid<Entity>, robots: &mut usize, pos: Coord, entity: Entity) {
+    if grid.get(pos) == &Entity::Robot {
+        *robots -= 1;
+    }
+    if entity == Entity::Robot {
+        *robots += 1;
+    }
+    grid.set(pos, entity);
+}
+
+/// Moves the robot and returns the cells that changed.
+fn record_move(warehouse: &mut Warehouse, dir: Direction) -> Vec<CellChange> {
+    let Some(affected) = warehouse.affected_by_push(warehouse.robot(), dir) else {
+        return vec![];
+    };
+
+    let mut cells: BTreeMap<Coord, (Entity, Entity)> = BTreeMap::new();
+    for &id in affected.iter() {
+        for &(pos, entity) in warehouse.body(id).cells.iter() {
+            cells.entry(pos).or_insert((Entity::None, Entity::None)).0 = entity;
+        }
+    }
+
+    warehouse.move_robot(dir);
+
+    for &id in affected.iter() {
+        for &(pos, entity) in warehouse.body(id).cells.iter() {
+            cells.entry(pos).or_insert((Entity::None, Entity::None)).1 = entity;
+        }
+    }
+
+    cells
+        .into_iter()
+        .filter(|(_, (before, after))| before != after)
+        .map(|(pos, (before, after))| CellChange { pos, before, after })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::push::Body;
+
+    #[test]
+    fn reports_first_violation_and_steps_back() {
+        // Wide box halves that are separate bodies can be pulled apart
+        let bodies = vec![
+            Body::single((2, 1), Entity::Robot),
+            Body::single((1, 1), Entity::WideBoxLeft),
+            Body::single((1, 2), Entity::WideBoxRight),
+        ];
+        let mut warehouse = Warehouse::new(Grid::zeros(4, 4), bodies, 0).unwrap();
+        let initial = warehouse.to_grid();
+
+        let moves = [Direction::Right, Direction::Left, Direction::Up];
+        let mut history = History::record(&mut warehouse, moves);
+
+        let violation = history.first_violation().unwrap();
+        assert_eq!(violation.index, 2);
+        assert!(matches!(
+            violation.error,
+            InvariantError::UnmatchedWideBox((0, 1))
+        ));
+
+        while history.step_back().is_some() {}
+        assert_eq!(history.position(), 0);
+        assert!(history.grid().iter().eq(initial.iter()));
+    }
+}
@@ -1,10 +1,17 @@
-use std::collections::HashSet;
+mod history;
+mod push;
+mod scale;
+
+use std::io::Write;
 
 use aoc_2024::{
     input::read_lines,
     utils::{Direction, Grid},
 };
+use history::History;
 use itertools::Itertools;
+use push::Warehouse;
+use scale::{ExpandError, TileExpansion};
 
 #[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
 enum Entity {
@@ -14,6 +21,7 @@ enum Entity {
     Wall,
     Robot,
     WideBoxLeft,
+    WideBoxMiddle,
     WideBoxRight,
 }
 
@@ -33,54 +41,39 @@ impl TryFrom<&u8> for Entity {
             &b'#' => Ok(Entity::Wall),
             &b'@' => Ok(Entity::Robot),
             &b'[' => Ok(Entity::WideBoxLeft),
+            &b'=' => Ok(Entity::WideBoxMiddle),
             &b']' => Ok(Entity::WideBoxRight),
             _ => Err(ParseEntityError::OutOfRange(*value)),
         }
     }
 }
 
-fn parse_map(lines: &[String]) -> Result<(Grid<Entity>, (usize, usize)), ParseEntityError> {
+fn parse_map(lines: &[String]) -> Result<Grid<Entity>, ParseEntityError> {
     assert!(!lines.is_empty());
     assert!(!lines[0].is_empty());
 
     let (rows, cols) = (lines.len(), lines[0].len());
 
     let mut world = Grid::<Entity>::zeros(rows, cols);
-    let mut robot_pos = None;
-
-    lines.iter().enumerate().for_each(|(i, line)| {
-        line.as_bytes().iter().enumerate().for_each(|(j, c)| {
-            if c == &b'@' {
-                robot_pos = Some((i, j));
-            }
-            world.set((i, j), c.try_into().unwrap());
-        })
-    });
 
-    assert!(robot_pos.is_some());
+    for (i, line) in lines.iter().enumerate() {
+        for (j, c) in line.as_bytes().iter().enumerate() {
+            world.set((i, j), c.try_into()?);
+        }
+    }
 
-    Ok((world, robot_pos.unwrap()))
+    Ok(world)
 }
 
-fn transform_map_to_wide(lines: &[String]) -> Vec<String> {
-    lines
-        .iter()
-        .map(|row| {
-            row.as_bytes()
-                .iter()
-                .map(|c| match c {
-                    &b'#' => "##",
-                    &b'.' => "..",
-                    &b'O' => "[]",
-                    &b'@' => "@.",
-                    _ => unreachable!(),
-                })
-                .join("")
-        })
-        .collect()
+/// Parses a `HEIGHTxWIDTH` scale such as `2x3`.
+fn parse_scale(scale: &str) -> Result<(usize, usize), String> {
+    scale
+        .split_once('x')
+        .and_then(|(h, w)| Some((h.parse().ok()?, w.parse().ok()?)))
+        .filter(|&(h, w)| h > 0 && w > 0)
+        .ok_or_else(|| format!("invalid scale {:?}, expected HEIGHTxWIDTH", scale))
 }
 
-#[allow(dead_code)]
 fn print_world(world: &Grid<Entity>) {
     world.rows().for_each(|row| {
         println!(
@@ -92,6 +85,7 @@ fn print_world(world: &Grid<Entity>) {
                     Entity::Wall => "#",
                     Entity::Robot => "@",
                     Entity::WideBoxLeft => "[",
+                    Entity::WideBoxMiddle => "=",
                     Entity::WideBoxRight => "]",
                 })
                 .join("")
@@ -99,7 +93,7 @@ fn print_world(world: &Grid<Entity>) {
     });
 }
 
-fn simulate_robot(world: &mut Grid<Entity>, mut robot_pos: (usize, usize), moves: &String) {
+fn simulate_robot(warehouse: &mut Warehouse, moves: &str) -> History {
     let move_to_dir = |mv: &u8| match mv {
         b'^' => Direction::Up,
         b'v' => Direction::Down,
@@ -108,183 +102,127 @@ fn simulate_robot(world: &mut Grid<Entity>, mut robot_pos: (usize, usize), moves
         _ => unreachable!(),
     };
 
-    for mv in moves.as_bytes() {
-        assert!(world.get(robot_pos) == &Entity::Robot);
-
-        // Find boxes or walls
-        let dir = move_to_dir(mv);
-        let dir_vec = dir.to_coord_vec();
-
-        let mut curr = dir_vec
-            .transform_coord(robot_pos, world.size())
-            .expect("Robot is inside a wall");
-
-        if world.get(curr) == &Entity::None {
-            world.set(curr, Entity::Robot);
-            world.set(robot_pos, Entity::None);
-            robot_pos = curr;
-        } else if world.get(curr) == &Entity::Box {
-            loop {
-                curr = dir_vec
-                    .transform_coord(curr, world.size())
-                    .expect("Moved out-of-bounds");
-
-                if world.get(curr) == &Entity::None {
-                    world.set(curr, Entity::Box);
-                    curr = dir_vec
-                        .transform_coord(robot_pos, world.size())
-                        .expect("Moved out-of-bounds when setting robot pos");
-                    world.set(curr, Entity::Robot);
-                    world.set(robot_pos, Entity::None);
-                    robot_pos = curr;
-                    break;
-                } else if world.get(curr) == &Entity::Wall {
-                    break;
+    let history = History::record(warehouse, moves.as_bytes().iter().map(move_to_dir));
+    if let Some(violation) = history.first_violation() {
+        println!("{}", violation);
+    }
+
+    history
+}
+
+/// Steps through the recorded moves: Enter or `n` for the next move, `p`
+/// for the previous one, a number to jump to that move and `q` to quit.
+fn step_through(history: &mut History) {
+    let mut line = String::new();
+    loop {
+        print_world(history.grid());
+        println!("Move {}/{}", history.position(), history.len());
+        print!("[n]ext, [p]revious, <move>, [q]uit: ");
+        std::io::stdout().flush().unwrap();
+
+        line.clear();
+        if std::io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+
+        match line.trim() {
+            "" | "n" => {
+                if let Some(record) = history.step_forward() {
+                    println!("{:?}, changed {:?}", record.dir, record.changes);
                 }
             }
-        } else if [Entity::WideBoxLeft, Entity::WideBoxRight].contains(world.get(curr)) {
-            if [Direction::Left, Direction::Right].contains(&dir) {
-                let mut entities = vec![*world.get(curr)];
-                loop {
-                    curr = dir_vec
-                        .transform_coord(curr, world.size())
-                        .expect("Moved out-of-bounds");
-
-                    if world.get(curr) == &Entity::None {
-                        curr = dir_vec.transform_coord(robot_pos, world.size()).unwrap();
-                        world.set(robot_pos, Entity::None);
-                        world.set(curr, Entity::Robot);
-                        robot_pos = curr;
-
-                        for i in 0..entities.len() {
-                            curr = dir_vec.transform_coord(curr, world.size()).unwrap();
-                            world.set(curr, entities[i]);
-                        }
-                        break;
-                    } else if world.get(curr) == &Entity::Wall {
-                        break;
-                    } else {
-                        entities.push(*world.get(curr));
-                    }
-                }
-            } else {
-                let mut entity_rows = {
-                    let mut row_entities = HashSet::new();
-                    if world.get(curr) == &Entity::WideBoxLeft {
-                        let pos = Direction::Right
-                            .to_coord_vec()
-                            .transform_coord(curr, world.size())
-                            .unwrap();
-                        assert!(world.get(pos) == &Entity::WideBoxRight);
-                        row_entities.insert((curr, Entity::WideBoxLeft));
-                        row_entities.insert((pos, Entity::WideBoxRight));
-                        vec![row_entities]
-                    } else {
-                        let pos = Direction::Left
-                            .to_coord_vec()
-                            .transform_coord(curr, world.size())
-                            .unwrap();
-                        assert!(world.get(pos) == &Entity::WideBoxLeft);
-                        row_entities.insert((pos, Entity::WideBoxLeft));
-                        row_entities.insert((curr, Entity::WideBoxRight));
-                        vec![row_entities]
-                    }
-                };
-
-                'outer: loop {
-                    let last_row = entity_rows.last().unwrap();
-                    let mut row_entities = HashSet::new();
-                    for (pos, _) in last_row.iter() {
-                        let new_pos = dir_vec.transform_coord(*pos, world.size()).unwrap();
-                        if world.get(new_pos) == &Entity::WideBoxLeft {
-                            let pos = Direction::Right
-                                .to_coord_vec()
-                                .transform_coord(new_pos, world.size())
-                                .unwrap();
-
-                            assert!(world.get(pos) == &Entity::WideBoxRight);
-                            row_entities.insert((new_pos, Entity::WideBoxLeft));
-                            row_entities.insert((pos, Entity::WideBoxRight));
-                        } else if world.get(new_pos) == &Entity::WideBoxRight {
-                            let pos = Direction::Left
-                                .to_coord_vec()
-                                .transform_coord(new_pos, world.size())
-                                .unwrap();
-
-                            assert!(world.get(pos) == &Entity::WideBoxLeft);
-                            row_entities.insert((pos, Entity::WideBoxLeft));
-                            row_entities.insert((new_pos, Entity::WideBoxRight));
-                        } else if world.get(new_pos) == &Entity::Wall {
-                            entity_rows.clear();
-                            break 'outer;
-                        }
-                    }
-
-                    if row_entities.is_empty() {
-                        break;
-                    }
-
-                    entity_rows.push(row_entities);
-                }
-
-                if !entity_rows.is_empty() {
-                    while let Some(row_entities) = entity_rows.pop() {
-                        for (old_pos, entity) in row_entities {
-                            let new_pos = dir_vec.transform_coord(old_pos, world.size()).unwrap();
-                            world.set(new_pos, entity);
-                            world.set(old_pos, Entity::None);
-                        }
-                    }
-
-                    curr = dir_vec.transform_coord(robot_pos, world.size()).unwrap();
-                    world.set(robot_pos, Entity::None);
-                    world.set(curr, Entity::Robot);
-                    robot_pos = curr;
+            "p" => {
+                history.step_back();
+            }
+            "q" => break,
+            target => {
+                if let Ok(target) = target.parse::<usize>() {
+                    while history.position() > target && history.step_back().is_some() {}
+                    while history.position() < target && history.step_forward().is_some() {}
                 }
             }
         }
-
-        //print_world(world);
-        //pause();
     }
 }
 
-fn main() {
+/// Splits the input into the map lines and the moves, joined into one line.
+fn split_input(mut lines: impl Iterator<Item = String>) -> (Vec<String>, String) {
+    let world_input = lines
+        .by_ref()
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+
+    (world_input, lines.join(""))
+}
+
+/// The second star's warehouse, everything except the robot twice as wide.
+fn wide_expansion() -> Result<TileExpansion, ExpandError> {
+    TileExpansion::new(1, 2)
+        .tile(Entity::Wall, "##")
+        .and_then(|e| e.tile(Entity::None, ".."))
+        .and_then(|e| e.tile(Entity::Box, "[]"))
+        .and_then(|e| e.tile(Entity::Robot, "@."))
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     if let Ok(lines) = read_lines("./inputs/day15/input.txt") {
         // Parsing
-        let mut input = lines.flatten().into_iter();
+        let (world_input, moves) = split_input(lines.map_while(Result::ok));
+        let args = std::env::args().collect::<Vec<_>>();
+        let step = args.iter().any(|arg| arg == "--step");
+        let scale = args
+            .iter()
+            .position(|arg| arg == "--scale")
+            .map(|i| parse_scale(args.get(i + 1).map_or("2x2", |s| s.as_str())))
+            .transpose()?;
 
-        let mut world_input = vec![];
-        while let Some(line) = input.next() {
-            if line.is_empty() {
-                break;
-            }
+        let world = parse_map(&world_input)?;
+        let mut warehouse = Warehouse::from_grid(&world)?;
 
-            world_input.push(line);
-        }
+        simulate_robot(&mut warehouse, &moves);
 
-        let moves = input.join("");
+        println!("Star 1: {}", warehouse.gps_sum());
 
-        let (mut world, robot_pos) = parse_map(&world_input).unwrap();
+        let mut warehouse = wide_expansion()?.warehouse(&world)?;
 
-        simulate_robot(&mut world, robot_pos, &moves);
+        let mut history = simulate_robot(&mut warehouse, &moves);
 
-        let res = world
-            .indexed_iter()
-            .filter_map(|((i, j), entity)| (entity == &Entity::Box).then_some(i * 100 + j))
-            .sum::<usize>();
+        println!("Star 2: {}", warehouse.gps_sum());
 
-        println!("Star 1: {}", res);
+        if let Some((height, width)) = scale {
+            let mut warehouse = TileExpansion::scaled(height, width).warehouse(&world)?;
+            simulate_robot(&mut warehouse, &moves);
 
-        let (mut world, robot_pos) = parse_map(&transform_map_to_wide(&world_input)).unwrap();
+            println!("Scaled {}x{}: {}", height, width, warehouse.gps_sum());
+        }
 
-        simulate_robot(&mut world, robot_pos, &moves);
+        if step {
+            while history.step_back().is_some() {}
+            step_through(&mut history);
+        }
+    }
 
-        let res = world
-            .indexed_iter()
-            .filter_map(|((i, j), entity)| (entity == &Entity::WideBoxLeft).then_some(i * 100 + j))
-            .sum::<usize>();
+    Ok(())
+}
 
-        println!("Star 2: {}", res);
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn example_gps_sums() {
+        let lines = read_lines("./inputs/day15/test.txt").unwrap();
+        let (world_input, moves) = split_input(lines.map_while(Result::ok));
+        let world = parse_map(&world_input).unwrap();
+
+        let mut warehouse = Warehouse::from_grid(&world).unwrap();
+        let history = simulate_robot(&mut warehouse, &moves);
+        assert!(history.first_violation().is_none());
+        assert_eq!(warehouse.gps_sum(), 10092);
+
+        let mut warehouse = wide_expansion().unwrap().warehouse(&world).unwrap();
+        let history = simulate_robot(&mut warehouse, &moves);
+        assert!(history.first_violation().is_none());
+        assert_eq!(warehouse.gps_sum(), 9021);
     }
 }
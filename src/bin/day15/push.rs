@@ -0,0 +1,247 @@
+use std::collections::{HashSet, VecDeque};
+
+use aoc_2024::utils::{Coord, Direction, Grid};
+
+use crate::Entity;
+
+pub type BodyId = usize;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShapeError {
+    #[error("no robot in the warehouse")]
+    MissingRobot,
+    #[error("unmatched wide box half at {0:?}")]
+    UnmatchedWideBox(Coord),
+    #[error("bodies overlap at {0:?}")]
+    Overlap(Coord),
+}
+
+/// A rigid object made of one or more cells, each with the tile it is
+/// drawn as. Pushing any cell of a body moves the whole body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Body {
+    pub cells: Vec<(Coord, Entity)>,
+}
+
+impl Body {
+    pub fn single(pos: Coord, entity: Entity) -> Self {
+        Self {
+            cells: vec![(pos, entity)],
+        }
+    }
+
+    /// Top-left cell of the body's bounding box.
+    pub fn anchor(&self) -> Coord {
+        let row = self.cells.iter().map(|((r, _), _)| *r).min().unwrap();
+        let col = self.cells.iter().map(|((_, c), _)| *c).min().unwrap();
+        (row, col)
+    }
+}
+
+/// Walls plus a set of rigid bodies, one of which is the robot.
+#[derive(Clone)]
+pub struct Warehouse {
+    walls: Grid<bool>,
+    occupancy: Grid<Option<BodyId>>,
+    bodies: Vec<Body>,
+    robot: BodyId,
+}
+
+impl Warehouse {
+    pub fn new(walls: Grid<bool>, bodies: Vec<Body>, robot: BodyId) -> Result<Self, ShapeError> {
+        let (rows, cols) = walls.size();
+        let mut occupancy = Grid::<Option<BodyId>>::zeros(rows, cols);
+
+        for (id, body) in bodies.iter().enumerate() {
+            for &(pos, _) in body.cells.iter() {
+                if *walls.get(pos) || occupancy.get(pos).is_some() {
+                    return Err(ShapeError::Overlap(pos));
+                }
+                occupancy.set(pos, Some(id));
+            }
+        }
+
+        Ok(Self {
+            walls,
+            occupancy,
+            bodies,
+            robot,
+        })
+    }
+
+    /// Builds bodies from the puzzle tiles: `O` and `@` are single cells,
+    /// `[`, any `=` and `]` join up into a wide box.
+    pub fn from_grid(grid: &Grid<Entity>) -> Result<Self, ShapeError> {
+        let (rows, cols) = grid.size();
+        let mut walls = Grid::zeros(rows, cols);
+        let mut bodies = vec![];
+        let mut robot = None;
+
+        for (pos, &entity) in grid.indexed_iter() {
+            match entity {
+                Entity::None => (),
+                Entity::Wall => walls.set(pos, true),
+                Entity::Box => bodies.push(Body::single(pos, entity)),
+                Entity::Robot => {
+                    robot = Some(bodies.len());
+                    bodies.push(Body::single(pos, entity));
+                }
+                Entity::WideBoxLeft => {
+                    let mut cells = vec![(pos, entity)];
+                    let mut next = pos;
+                    loop {
+                        next = Direction::Right
+                            .step(next, grid.size())
+                            .ok_or(ShapeError::UnmatchedWideBox(pos))?;
+                        match grid.get(next) {
+                            Entity::WideBoxMiddle => cells.push((next, Entity::WideBoxMiddle)),
+                            Entity::WideBoxRight => break cells.push((next, Entity::WideBoxRight)),
+                            _ => return Err(ShapeError::UnmatchedWideBox(pos)),
+                        }
+                    }
+                    bodies.push(Body { cells });
+                }
+                Entity::WideBoxMiddle | Entity::WideBoxRight => {
+                    let matched = Direction::Left.step(pos, grid.size()).is_some_and(|left| {
+                        matches!(grid.get(left), Entity::WideBoxLeft | Entity::WideBoxMiddle)
+                    });
+                    if !matched {
+                        return Err(ShapeError::UnmatchedWideBox(pos));
+                    }
+                }
+            }
+        }
+
+        Self::new(walls, bodies, robot.ok_or(ShapeError::MissingRobot)?)
+    }
+
+    pub fn to_grid(&self) -> Grid<Entity> {
+        let (rows, cols) = self.walls.size();
+        let mut grid = Grid::zeros(rows, cols);
+
+        for (pos, &wall) in self.walls.indexed_iter() {
+            if wall {
+                grid.set(pos, Entity::Wall);
+            }
+        }
+        for body in self.bodies.iter() {
+            for &(pos, entity) in body.cells.iter() {
+                grid.set(pos, entity);
+            }
+        }
+
+        grid
+    }
+
+    pub fn robot(&self) -> BodyId {
+        self.robot
+    }
+
+    pub fn body(&self, id: BodyId) -> &Body {
+        &self.bodies[id]
+    }
+
+    /// All bodies that move when `body` is pushed one step in `dir`, found
+    /// by a BFS over the cells in front of every moving body. Returns `None`
+    /// if any of them would hit a wall or leave the map.
+    pub fn affected_by_push(&self, body: BodyId, dir: Direction) -> Option<Vec<BodyId>> {
+        let mut affected = vec![body];
+        let mut seen = HashSet::from([body]);
+        let mut queue = VecDeque::from([body]);
+
+        while let Some(id) = queue.pop_front() {
+            for &(pos, _) in self.bodies[id].cells.iter() {
+                let next = dir.step(pos, self.walls.size())?;
+                if *self.walls.get(next) {
+                    return None;
+                }
+
+                if let Some(other) = *self.occupancy.get(next) {
+                    if seen.insert(other) {
+                        affected.push(other);
+                        queue.push_back(other);
+                    }
+                }
+            }
+        }
+
+        Some(affected)
+    }
+
+    /// Pushes `body` one step in `dir` together with everything in its way.
+    /// Either all affected bodies move or none do. Returns the moved bodies.
+    pub fn push(&mut self, body: BodyId, dir: Direction) -> Option<Vec<BodyId>> {
+        let affected = self.affected_by_push(body, dir)?;
+        let bounds = self.walls.size();
+
+        for &id in affected.iter() {
+            for &(pos, _) in self.bodies[id].cells.iter() {
+                self.occupancy.set(pos, None);
+            }
+        }
+        for &id in affected.iter() {
+            for (pos, _) in self.bodies[id].cells.iter_mut() {
+                // Checked by `affected_by_push`
+                *pos = dir.step(*pos, bounds).unwrap();
+                self.occupancy.set(*pos, Some(id));
+            }
+        }
+
+        Some(affected)
+    }
+
+    pub fn move_robot(&mut self, dir: Direction) -> Option<Vec<BodyId>> {
+        self.push(self.robot, dir)
+    }
+
+    /// Sum of `100 * row + col` over the top-left corner of every box.
+    pub fn gps_sum(&self) -> usize {
+        self.bodies
+            .iter()
+            .enumerate()
+            .filter(|(id, _)| *id != self.robot)
+            .map(|(_, body)| body.anchor())
+            .map(|(row, col)| row * 100 + col)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn push_arbitrary_shapes() {
+        let mut walls = Grid::zeros(7, 8);
+        (0..8).for_each(|col| walls.set((0, col), true));
+        let bodies = vec![
+            Body::single((5, 2), Entity::Robot),
+            // 3-wide box right above the robot
+            Body {
+                cells: vec![
+                    ((4, 1), Entity::WideBoxLeft),
+                    ((4, 2), Entity::Box),
+                    ((4, 3), Entity::WideBoxRight),
+                ],
+            },
+            // L-shaped box resting on the right end of the 3-wide box
+            Body {
+                cells: vec![
+                    ((3, 3), Entity::Box),
+                    ((3, 4), Entity::Box),
+                    ((2, 4), Entity::Box),
+                ],
+            },
+        ];
+        let mut warehouse = Warehouse::new(walls, bodies, 0).unwrap();
+
+        assert_eq!(warehouse.move_robot(Direction::Up), Some(vec![0, 1, 2]));
+        // The L-shaped box now touches the wall, nothing moves
+        assert_eq!(warehouse.move_robot(Direction::Up), None);
+        assert_eq!(warehouse.bodies[1].anchor(), (3, 1));
+        assert_eq!(warehouse.bodies[2].anchor(), (1, 3));
+
+        assert_eq!(warehouse.move_robot(Direction::Right), Some(vec![0]));
+        assert_eq!(warehouse.gps_sum(), 301 + 103);
+    }
+}
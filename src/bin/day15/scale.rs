@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use aoc_2024::utils::{Coord, Grid};
+
+use crate::{
+    push::{Body, ShapeError, Warehouse},
+    Entity, ParseEntityError,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExpandError {
+    #[error("no expansion defined for {entity:?} at {pos:?}")]
+    UnknownTile { entity: Entity, pos: Coord },
+    #[error("block {block:?} for {entity:?} is not {height}x{width}")]
+    BlockSize {
+        entity: Entity,
+        block: String,
+        height: usize,
+        width: usize,
+    },
+    #[error("expected exactly one robot, found {0}")]
+    RobotCount(usize),
+    #[error(transparent)]
+    Parse(#[from] ParseEntityError),
+    #[error(transparent)]
+    Shape(#[from] ShapeError),
+}
+
+/// Maps every source tile to a `height` x `width` block of tiles, defined
+/// per entity. Tiles without a block are rejected.
+pub struct TileExpansion {
+    height: usize,
+    width: usize,
+    blocks: HashMap<Entity, Vec<Entity>>,
+}
+
+impl TileExpansion {
+    pub fn new(height: usize, width: usize) -> Self {
+        assert!(height > 0 && width > 0);
+
+        Self {
+            height,
+            width,
+            blocks: HashMap::new(),
+        }
+    }
+
+    /// Scales walls, floor, robot and boxes of the narrow map. Boxes become a
+    /// single rigid block drawn as `[==]` rows, or `O` if one tile wide. The
+    /// robot takes the top-left tile of its block. `scaled(1, 2)` is the
+    /// puzzle's wide warehouse.
+    pub fn scaled(height: usize, width: usize) -> Self {
+        let mut expansion = Self::new(height, width);
+        let area = height * width;
+
+        let box_row = match width {
+            1 => vec![Entity::Box],
+            _ => std::iter::once(Entity::WideBoxLeft)
+                .chain(std::iter::repeat_n(Entity::WideBoxMiddle, width - 2))
+                .chain(std::iter::once(Entity::WideBoxRight))
+                .collect(),
+        };
+        let mut robot = vec![Entity::None; area];
+        robot[0] = Entity::Robot;
+
+        expansion
+            .blocks
+            .insert(Entity::None, vec![Entity::None; area]);
+        expansion
+            .blocks
+            .insert(Entity::Wall, vec![Entity::Wall; area]);
+        expansion.blocks.insert(Entity::Robot, robot);
+        expansion.blocks.insert(Entity::Box, box_row.repeat(height));
+
+        expansion
+    }
+
+    /// Sets the block for `entity`, written as rows of tile characters
+    /// separated by `/`, e.g. `"[]/[]"` for a 2x2 box.
+    pub fn tile(mut self, entity: Entity, block: &str) -> Result<Self, ExpandError> {
+        let rows = block.split('/').collect::<Vec<_>>();
+        if rows.len() != self.height || rows.iter().any(|row| row.len() != self.width) {
+            return Err(ExpandError::BlockSize {
+                entity,
+                block: block.to_string(),
+                height: self.height,
+                width: self.width,
+            });
+        }
+
+        let tiles = rows
+            .iter()
+            .flat_map(|row| row.as_bytes())
+            .map(Entity::try_from)
+            .collect::<Result<_, _>>()?;
+        self.blocks.insert(entity, tiles);
+
+        Ok(self)
+    }
+
+    pub fn expand(&self, grid: &Grid<Entity>) -> Result<Grid<Entity>, ExpandError> {
+        grid.expand((self.height, self.width), |pos, &entity| {
+            self.blocks
+                .get(&entity)
+                .map(|block| block.iter().copied())
+                .ok_or(ExpandError::UnknownTile { entity, pos })
+        })
+    }
+
+    /// Expands `grid` and turns every expanded tile into one rigid body, so
+    /// a scaled box moves as a whole. Walls stay walls and the robot stays a
+    /// single cell.
+    pub fn warehouse(&self, grid: &Grid<Entity>) -> Result<Warehouse, ExpandError> {
+        let expanded = self.expand(grid)?;
+        let (rows, cols) = expanded.size();
+        let mut walls = Grid::zeros(rows, cols);
+        let mut bodies = vec![];
+        let mut robots = vec![];
+
+        for ((row, col), _) in grid.indexed_iter() {
+            let mut body = Body { cells: vec![] };
+            for i in 0..self.height * self.width {
+                let pos = (
+                    row * self.height + i / self.width,
+                    col * self.width + i % self.width,
+                );
+                match *expanded.get(pos) {
+                    Entity::None => (),
+                    Entity::Wall => walls.set(pos, true),
+                    Entity::Robot => {
+                        robots.push(bodies.len());
+                        bodies.push(Body::single(pos, Entity::Robot));
+                    }
+                    entity => body.cells.push((pos, entity)),
+                }
+            }
+
+            if !body.cells.is_empty() {
+                bodies.push(body);
+            }
+        }
+
+        if robots.len() != 1 {
+            return Err(ExpandError::RobotCount(robots.len()));
+        }
+
+        Ok(Warehouse::new(walls, bodies, robots[0])?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{parse_map, parse_scale};
+    use aoc_2024::utils::Direction;
+
+    fn map(rows: &[&str]) -> Grid<Entity> {
+        parse_map(&rows.iter().map(|row| row.to_string()).collect::<Vec<_>>()).unwrap()
+    }
+
+    #[test]
+    fn scaled_boxes_move_as_one_body() {
+        let grid = map(&["#####", "#.O@#", "#####"]);
+
+        let expansion = TileExpansion::scaled(2, 3);
+        let expanded = expansion.expand(&grid).unwrap();
+        assert_eq!(expanded.size(), (6, 15));
+        assert_eq!(expanded.row(2)[6..9], expanded.row(3)[6..9]);
+        assert_eq!(
+            &expanded.row(3)[6..9],
+            &[
+                Entity::WideBoxLeft,
+                Entity::WideBoxMiddle,
+                Entity::WideBoxRight
+            ]
+        );
+
+        let mut warehouse = expansion.warehouse(&grid).unwrap();
+        assert_eq!(warehouse.gps_sum(), 206);
+        assert_eq!(
+            warehouse.move_robot(Direction::Left).map(|m| m.len()),
+            Some(2)
+        );
+        assert_eq!(warehouse.gps_sum(), 205);
+        // Pushing the top row of the box drags the bottom row along
+        for _ in 0..2 {
+            warehouse.move_robot(Direction::Left);
+        }
+        assert_eq!(warehouse.gps_sum(), 203);
+        assert_eq!(warehouse.move_robot(Direction::Left), None);
+    }
+
+    #[test]
+    fn rejects_unknown_tiles() {
+        let wide = map(&["#[]@#"]);
+        assert!(matches!(
+            TileExpansion::scaled(1, 2).expand(&wide),
+            Err(ExpandError::UnknownTile {
+                entity: Entity::WideBoxLeft,
+                pos: (0, 1)
+            })
+        ));
+
+        assert!(matches!(
+            TileExpansion::new(2, 2).tile(Entity::Box, "[]"),
+            Err(ExpandError::BlockSize { .. })
+        ));
+        assert!(matches!(
+            TileExpansion::new(1, 2).tile(Entity::Box, "<>"),
+            Err(ExpandError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn scale_arguments() {
+        assert_eq!(parse_scale("2x3"), Ok((2, 3)));
+        for invalid in ["2", "2x", "x3", "0x2", "2x0", "-1x2", "2y3"] {
+            assert!(parse_scale(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use aoc_2024::utils::{Coord, Direction};
+
+/// What moving through the maze costs. Turning happens in place, a U-turn
+/// without its own cost counts as two turns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CostModel {
+    pub step: usize,
+    pub turn: usize,
+    pub u_turn: Option<usize>,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            step: 1,
+            turn: 1000,
+            u_turn: None,
+        }
+    }
+}
+
+impl CostModel {
+    /// Cheapest way to face `to` when facing `from`.
+    pub fn turn_cost(&self, from: Direction, to: Direction) -> usize {
+        if from == to {
+            0
+        } else if from.opposite() == to {
+            self.u_turn_cost()
+        } else {
+            self.turn
+        }
+    }
+
+    /// Cost of turning to `to` and taking one step.
+    pub fn move_cost(&self, from: Direction, to: Direction) -> usize {
+        self.turn_cost(from, to) + self.step
+    }
+
+    fn u_turn_cost(&self) -> usize {
+        let twice = 2 * self.turn;
+        self.u_turn.map_or(twice, |u_turn| u_turn.min(twice))
+    }
+
+    /// Lower bound on the cost from `pos` facing `dir` to `goal`, ignoring
+    /// walls: the Manhattan distance plus the turns any path needs.
+    pub fn heuristic(&self, pos: Coord, dir: Direction, goal: Coord) -> usize {
+        let (dr, dc) = (
+            goal.0 as isize - pos.0 as isize,
+            goal.1 as isize - pos.1 as isize,
+        );
+        let steps = dr.unsigned_abs() + dc.unsigned_abs();
+
+        // Distance to the goal along and across the facing direction
+        let (ahead, across) = match dir {
+            Direction::Up => (-dr, dc),
+            Direction::Down => (dr, dc),
+            Direction::Left => (-dc, dr),
+            Direction::Right => (dc, dr),
+        };
+        let turns = match (ahead.signum(), across) {
+            (_, 0) if ahead >= 0 => 0,
+            (-1, 0) => self.u_turn_cost(),
+            (-1, _) => self.turn + self.turn.min(self.u_turn_cost()),
+            _ => self.turn,
+        };
+
+        steps * self.step + turns
+    }
+}
@@ -0,0 +1,204 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use aoc_2024::utils::{Coord, Direction, Grid, DIRECTIONS};
+
+use crate::{cost::CostModel, find_entity, Entity};
+
+pub type NodeId = usize;
+
+/// A corridor between two nodes.
+#[derive(Debug, Clone)]
+pub struct Edge {
+    pub to: NodeId,
+    /// Direction of the first step out of the node the edge starts at.
+    pub leave: Direction,
+    /// Direction of the last step into `to`.
+    pub arrive: Direction,
+    pub steps: usize,
+    /// Corners along the corridor.
+    pub turns: usize,
+    /// Cells strictly between the two nodes.
+    pub cells: Vec<Coord>,
+}
+
+impl Edge {
+    pub fn cost(&self, costs: &CostModel) -> usize {
+        self.steps * costs.step + self.turns * costs.turn
+    }
+}
+
+type State = (NodeId, Direction);
+
+/// The maze with every corridor contracted into a single edge. Nodes are
+/// junctions, dead ends, the start and the end.
+pub struct MazeGraph {
+    nodes: Vec<Coord>,
+    edges: Vec<Edge>,
+    outgoing: Vec<Vec<usize>>,
+    start: Option<NodeId>,
+    end: Option<NodeId>,
+}
+
+impl MazeGraph {
+    pub fn build(world: &Grid<Entity>) -> Self {
+        let open_neighbours = |pos: Coord| {
+            DIRECTIONS
+                .into_iter()
+                .filter(move |dir| {
+                    dir.step(pos, world.size())
+                        .is_some_and(|next| world.get(next) != &Entity::Wall)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut index = HashMap::new();
+        let mut nodes = vec![];
+        for (pos, &entity) in world.indexed_iter() {
+            let is_node = match entity {
+                Entity::Wall => false,
+                Entity::Start | Entity::End => true,
+                Entity::None => open_neighbours(pos).len() != 2,
+            };
+            if is_node {
+                index.insert(pos, nodes.len());
+                nodes.push(pos);
+            }
+        }
+
+        let mut edges = vec![];
+        let mut outgoing = vec![vec![]; nodes.len()];
+        for (from, &node) in nodes.iter().enumerate() {
+            for leave in open_neighbours(node) {
+                let mut dir = leave;
+                let mut pos = dir.step(node, world.size()).unwrap();
+                let (mut steps, mut turns, mut cells) = (1, 0, vec![]);
+
+                while !index.contains_key(&pos) {
+                    cells.push(pos);
+                    // Corridor cells have exactly two open neighbours
+                    let next = open_neighbours(pos)
+                        .into_iter()
+                        .find(|d| *d != dir.opposite())
+                        .unwrap();
+                    if next != dir {
+                        turns += 1;
+                    }
+                    dir = next;
+                    pos = dir.step(pos, world.size()).unwrap();
+                    steps += 1;
+                }
+
+                outgoing[from].push(edges.len());
+                edges.push(Edge {
+                    to: index[&pos],
+                    leave,
+                    arrive: dir,
+                    steps,
+                    turns,
+                    cells,
+                });
+            }
+        }
+
+        let node_of = |entity| find_entity(world, entity).map(|pos| index[&pos]);
+        Self {
+            start: node_of(Entity::Start),
+            end: node_of(Entity::End),
+            nodes,
+            edges,
+            outgoing,
+        }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Best score from start to end and every grid cell on a best path, or
+    /// `None` if the maze has no start, no end or no path between them.
+    pub fn solve(&self, costs: &CostModel) -> Option<(usize, HashSet<Coord>)> {
+        let (start, end) = (self.start?, self.end?);
+        let goal = self.nodes[end];
+
+        let mut distances: HashMap<State, usize> = HashMap::new();
+        // Edges that reach a state at its best score, for walking back
+        let mut predecessors: HashMap<State, Vec<(State, usize)>> = HashMap::new();
+        let mut q = BinaryHeap::new();
+
+        let initial = (start, Direction::Right);
+        distances.insert(initial, 0);
+        q.push(Reverse((
+            costs.heuristic(self.nodes[start], Direction::Right, goal),
+            0,
+            initial,
+        )));
+
+        let mut best: Option<usize> = None;
+        while let Some(Reverse((estimate, score, state))) = q.pop() {
+            if best.is_some_and(|best| estimate > best) {
+                break;
+            }
+            if score > distances[&state] {
+                continue;
+            }
+
+            let (node, dir) = state;
+            if node == end {
+                best.get_or_insert(score);
+                continue;
+            }
+
+            for &e in self.outgoing[node].iter() {
+                let edge = &self.edges[e];
+                if edge.to == start {
+                    continue;
+                }
+
+                let next = (edge.to, edge.arrive);
+                let next_score = score + costs.turn_cost(dir, edge.leave) + edge.cost(costs);
+                let known = distances.get(&next).copied().unwrap_or(usize::MAX);
+                if next_score > known {
+                    continue;
+                }
+                if next_score < known {
+                    distances.insert(next, next_score);
+                    predecessors.remove(&next);
+                    q.push(Reverse((
+                        next_score + costs.heuristic(self.nodes[edge.to], edge.arrive, goal),
+                        next_score,
+                        next,
+                    )));
+                }
+                predecessors.entry(next).or_default().push((state, e));
+            }
+        }
+
+        let best = best?;
+        let mut tiles = HashSet::from([self.nodes[start], goal]);
+        let mut stack = DIRECTIONS
+            .into_iter()
+            .map(|dir| (end, dir))
+            .filter(|state| distances.get(state) == Some(&best))
+            .collect::<Vec<_>>();
+        let mut seen = stack.iter().copied().collect::<HashSet<_>>();
+
+        while let Some(state) = stack.pop() {
+            for &(prev, e) in predecessors.get(&state).into_iter().flatten() {
+                tiles.insert(self.nodes[prev.0]);
+                tiles.extend(self.edges[e].cells.iter().copied());
+                if seen.insert(prev) {
+                    stack.push(prev);
+                }
+            }
+        }
+
+        Some((best, tiles))
+    }
+}
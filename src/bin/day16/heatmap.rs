@@ -0,0 +1,139 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashSet},
+    io::{self, Write},
+};
+
+use aoc_2024::utils::{Coord, Direction, Grid};
+
+use crate::{cost::CostModel, find_entity, find_options, Entity};
+
+/// Side length of a maze cell in pixels.
+const CELL_SIZE: usize = 4;
+
+type Rgb = [u8; 3];
+
+const WALL: Rgb = [48, 48, 48];
+const UNREACHED: Rgb = [0, 0, 0];
+const START: Rgb = [0, 220, 0];
+const END: Rgb = [230, 0, 0];
+const BEST_PATH: Rgb = [255, 255, 255];
+/// Colours for increasing distance, blended linearly.
+const GRADIENT: [Rgb; 4] = [[20, 20, 140], [0, 150, 220], [240, 220, 0], [200, 60, 0]];
+
+/// Minimum score to reach every cell from the start, over all directions
+/// the reindeer can face there. Unlike the solvers this explores the whole
+/// maze instead of stopping at the end.
+pub fn min_distances(world: &Grid<Entity>, costs: &CostModel) -> Grid<Option<usize>> {
+    let (rows, cols) = world.size();
+    let mut cells = Grid::<Option<usize>>::zeros(rows, cols);
+    let Some(start) = find_entity(world, Entity::Start) else {
+        return cells;
+    };
+
+    let mut settled: HashSet<(Coord, Direction)> = HashSet::new();
+    let mut q = BinaryHeap::from([Reverse((0, start, Direction::Right))]);
+
+    while let Some(Reverse((score, pos, dir))) = q.pop() {
+        if !settled.insert((pos, dir)) {
+            continue;
+        }
+        // States settle in order of score, so the first one wins
+        if cells.get(pos).is_none() {
+            cells.set(pos, Some(score));
+        }
+
+        for (next, next_dir) in find_options(world, pos) {
+            if !settled.contains(&(next, next_dir)) {
+                q.push(Reverse((
+                    score + costs.move_cost(dir, next_dir),
+                    next,
+                    next_dir,
+                )));
+            }
+        }
+    }
+
+    cells
+}
+
+/// Writes the distances as a binary PPM image, highlighting walls, the
+/// start, the end and every tile in `best_path`.
+pub fn write_ppm(
+    out: &mut impl Write,
+    world: &Grid<Entity>,
+    distances: &Grid<Option<usize>>,
+    best_path: &HashSet<Coord>,
+) -> io::Result<()> {
+    let (rows, cols) = world.size();
+    let max = distances.iter().flatten().max().copied().unwrap_or(0);
+
+    writeln!(out, "P6\n{} {}\n255", cols * CELL_SIZE, rows * CELL_SIZE)?;
+
+    for row in 0..rows {
+        let line = (0..cols)
+            .flat_map(|col| {
+                let colour = match (world.get((row, col)), distances.get((row, col))) {
+                    (Entity::Wall, _) => WALL,
+                    (Entity::Start, _) => START,
+                    (Entity::End, _) => END,
+                    _ if best_path.contains(&(row, col)) => BEST_PATH,
+                    (_, Some(distance)) => gradient(*distance, max),
+                    (_, None) => UNREACHED,
+                };
+                colour.repeat(CELL_SIZE)
+            })
+            .collect::<Vec<_>>();
+
+        for _ in 0..CELL_SIZE {
+            out.write_all(&line)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn gradient(value: usize, max: usize) -> Rgb {
+    let max = max.max(1);
+    let segments = GRADIENT.len() - 1;
+    // Position along the whole gradient, in units of `max` per segment
+    let scaled = value * segments;
+    let segment = (scaled / max).min(segments - 1);
+    let offset = scaled - segment * max;
+
+    let (from, to) = (GRADIENT[segment], GRADIENT[segment + 1]);
+    std::array::from_fn(|i| {
+        let (from, to) = (from[i] as usize, to[i] as usize);
+        ((from * (max - offset) + to * offset) / max) as u8
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn gradient_ends() {
+        assert_eq!(gradient(0, 30), GRADIENT[0]);
+        assert_eq!(gradient(10, 30), GRADIENT[1]);
+        assert_eq!(gradient(30, 30), GRADIENT[3]);
+        assert_eq!(gradient(0, 0), GRADIENT[0]);
+    }
+
+    #[test]
+    fn ppm_layout() {
+        let world = crate::parse_map(&["#####".into(), "#S.E#".into(), "#####".into()]).unwrap();
+        let distances = min_distances(&world, &CostModel::default());
+        assert_eq!(distances.row(1), &[None, Some(0), Some(1), Some(2), None]);
+
+        let mut image = vec![];
+        write_ppm(&mut image, &world, &distances, &HashSet::new()).unwrap();
+        let header = "P6\n20 12\n255\n";
+        assert!(image.starts_with(header.as_bytes()));
+        assert_eq!(image.len(), header.len() + 20 * 12 * 3);
+
+        // Middle of the floor tile between start and end
+        let pixel = header.len() + ((CELL_SIZE + 1) * 20 + 2 * CELL_SIZE + 1) * 3;
+        assert_eq!(image[pixel..pixel + 3], gradient(1, 2));
+    }
+}
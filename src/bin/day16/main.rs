@@ -1,9 +1,19 @@
-use std::collections::{BinaryHeap, HashMap, HashSet};
+mod cost;
+mod graph;
+mod heatmap;
+
+use std::{
+    collections::{BinaryHeap, HashMap, HashSet},
+    fs::File,
+    io::BufWriter,
+};
 
 use aoc_2024::{
     input::read_lines,
     utils::{Direction, Grid, DIRECTIONS},
 };
+use cost::CostModel;
+use graph::MazeGraph;
 use itertools::Itertools;
 
 #[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
@@ -90,54 +100,20 @@ fn print_world_and_tiles(world: &Grid<Entity>, tiles: &HashSet<(usize, usize)>)
     });
 }
 
-fn print_distances(distances: &HashMap<((usize, usize), Direction), usize>, world: &Grid<Entity>) {
-    world.rows().enumerate().for_each(|(i, row)| {
-        println!("{}", "+------".repeat(row.len()) + "+");
-        println!(
-            "{}",
-            row.iter()
-                .enumerate()
-                .map(|(j, entity)| {
-                    if let Some(d) = DIRECTIONS
-                        .iter()
-                        .filter_map(|d| distances.get(&((i, j), *d)))
-                        .min()
-                    {
-                        format!("|{:^6}", d)
-                    } else if entity == &Entity::Wall {
-                        format!("|######")
-                    } else {
-                        format!("|      ")
-                    }
-                })
-                .join("")
-                + "|"
-        );
-    });
-    println!("{}", "+------".repeat(world.row(0).len()) + "+");
-}
-
 #[derive(PartialEq, Eq, Clone)]
 struct PathElement {
     pub score: usize,
+    /// `score` plus the heuristic cost from the last position to the goal.
+    pub estimate: usize,
     pub path: Vec<((usize, usize), Direction)>,
-    pub goal: (usize, usize),
-}
-
-impl PathElement {
-    fn distance_to_goal(&self) -> usize {
-        let pos = self.path.last().unwrap().0;
-
-        pos.0.abs_diff(self.goal.0) + pos.1.abs_diff(self.goal.1)
-    }
 }
 
 impl Ord for PathElement {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         other
-            .score
-            .cmp(&self.score)
-            .then_with(|| other.distance_to_goal().cmp(&self.distance_to_goal()))
+            .estimate
+            .cmp(&self.estimate)
+            .then_with(|| other.score.cmp(&self.score))
             .then_with(|| self.path.last().unwrap().cmp(&other.path.last().unwrap()))
     }
 }
@@ -166,90 +142,99 @@ fn find_options(world: &Grid<Entity>, pos: (usize, usize)) -> Vec<((usize, usize
         .collect()
 }
 
-fn find_shortest_path(world: &Grid<Entity>) -> (usize, usize) {
-    let mut q: BinaryHeap<PathElement> = BinaryHeap::new();
-
-    let start = world
+fn find_entity(world: &Grid<Entity>, entity: Entity) -> Option<(usize, usize)> {
+    world
         .indexed_iter()
-        .find(|(_, e)| e == &&Entity::Start)
-        .unwrap()
-        .0;
+        .find(|(_, e)| e == &&entity)
+        .map(|(pos, _)| pos)
+}
 
-    let end = world
-        .indexed_iter()
-        .find(|(_, e)| e == &&Entity::Start)
-        .unwrap()
-        .0;
+/// Best score from start to end and every tile on a best path, or `None`
+/// if the maze has no start, no end or no path between them.
+fn find_shortest_path(
+    world: &Grid<Entity>,
+    costs: &CostModel,
+) -> Option<(usize, HashSet<(usize, usize)>)> {
+    let mut q: BinaryHeap<PathElement> = BinaryHeap::new();
+
+    let start = find_entity(world, Entity::Start)?;
+    let end = find_entity(world, Entity::End)?;
 
     let mut distances: HashMap<((usize, usize), Direction), usize> = HashMap::default();
 
     q.push(PathElement {
         score: 0,
+        estimate: costs.heuristic(start, Direction::Right, end),
         path: vec![(start, Direction::Right)],
-        goal: end,
     });
     distances.insert((start, Direction::Right), 0);
 
     let mut shortest_paths: Vec<PathElement> = vec![];
 
-    loop {
-        let path_elem = q.pop().unwrap();
-
-        if !shortest_paths.is_empty()
-            && path_elem.score > shortest_paths.first().as_ref().unwrap().score
+    while let Some(path_elem) = q.pop() {
+        // The heuristic is consistent, so nothing left can tie the best path
+        if shortest_paths
+            .first()
+            .is_some_and(|best| path_elem.estimate > best.score)
         {
-            dbg!(shortest_paths.first().as_ref().unwrap().score);
-            dbg!(path_elem.score);
-            dbg!(q.iter().map(|e| e.score).collect_vec());
             break;
         }
 
         let (curr_pos, curr_dir) = **path_elem.path.last().as_ref().unwrap();
 
-        if world.get(curr_pos) == &Entity::End {
+        if curr_pos == end {
             shortest_paths.push(path_elem);
             continue;
         }
 
         for (pos, dir) in find_options(world, curr_pos).into_iter() {
-            if dir == curr_dir {
-                if path_elem.score + 1 > *distances.get(&(pos, dir)).unwrap_or(&usize::MAX) {
-                    continue;
-                }
-
-                let mut new_elem = path_elem.clone();
-                new_elem.path.push((pos, dir));
-                new_elem.score += 1;
-
-                distances.insert((pos, dir), new_elem.score);
-                q.push(new_elem);
-            } else {
-                if path_elem.score + 1001 > *distances.get(&(pos, dir)).unwrap_or(&usize::MAX) {
-                    continue;
-                }
+            let score = path_elem.score + costs.move_cost(curr_dir, dir);
+            if score > *distances.get(&(pos, dir)).unwrap_or(&usize::MAX) {
+                continue;
+            }
 
-                let mut new_elem = path_elem.clone();
+            let mut new_elem = path_elem.clone();
+            if dir != curr_dir {
                 new_elem.path.push((curr_pos, dir));
-                new_elem.path.push((pos, dir));
-                new_elem.score += 1001;
+            }
+            new_elem.path.push((pos, dir));
+            new_elem.score = score;
+            new_elem.estimate = score + costs.heuristic(pos, dir, end);
 
-                distances.insert((pos, dir), new_elem.score);
-                q.push(new_elem);
-            };
+            distances.insert((pos, dir), score);
+            q.push(new_elem);
         }
     }
 
-    print_distances(&distances, &world);
-
     let mut tiles = HashSet::new();
 
     shortest_paths
         .iter()
         .for_each(|path| tiles.extend(path.path.iter().map(|(pos, _)| *pos)));
 
-    //print_world_and_tiles(world, &tiles);
+    Some((shortest_paths.first()?.score, tiles))
+}
 
-    (shortest_paths.first().unwrap().score, tiles.len())
+/// Parses `STEP,TURN[,UTURN]` into a cost model.
+fn parse_costs(costs: &str) -> Option<CostModel> {
+    let costs = costs
+        .split(',')
+        .map(|c| c.parse().ok())
+        .collect::<Option<Vec<usize>>>()?;
+
+    match costs[..] {
+        [step, turn] => Some(CostModel {
+            step,
+            turn,
+            u_turn: None,
+        }),
+        [step, turn, u_turn] => Some(CostModel {
+            step,
+            turn,
+            u_turn: Some(u_turn),
+        }),
+        _ => None,
+    }
 }
 
 fn main() {
@@ -259,9 +244,119 @@ fn main() {
         let world = parse_map(&input).expect("Failed to parse input");
         print_world(&world);
 
-        let score = find_shortest_path(&world);
+        let args = std::env::args().collect_vec();
+        let costs = match args.iter().position(|arg| arg == "--costs") {
+            Some(i) => args
+                .get(i + 1)
+                .and_then(|costs| parse_costs(costs))
+                .expect("Expected --costs STEP,TURN[,UTURN]"),
+            None => CostModel::default(),
+        };
+
+        let result = if args.iter().any(|arg| arg == "--grid") {
+            find_shortest_path(&world, &costs)
+        } else {
+            let graph = MazeGraph::build(&world);
+            println!(
+                "Graph: {} nodes, {} edges",
+                graph.node_count(),
+                graph.edge_count()
+            );
+            graph.solve(&costs)
+        };
+
+        match result {
+            Some((score, ref tiles)) => {
+                println!("Score: {}", score);
+                println!("Tiles: {}", tiles.len());
+            }
+            None => println!("No path from start to end"),
+        }
+
+        if let Some(i) = args.iter().position(|arg| arg == "--heatmap") {
+            let path = args.get(i + 1).map_or("day16.ppm", |path| path.as_str());
+            let distances = heatmap::min_distances(&world, &costs);
+            let best_path = result.map(|(_, tiles)| tiles).unwrap_or_default();
 
-        println!("Score: {}", score.0);
-        println!("Tiles: {}", score.1);
+            let mut out = BufWriter::new(File::create(path).expect("Failed to create heat map"));
+            heatmap::write_ppm(&mut out, &world, &distances, &best_path)
+                .expect("Failed to write heat map");
+            println!("Heat map written to {}", path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: &str = "###############
+#.......#....E#
+#.#.###.#.###.#
+#.....#.#...#.#
+#.###.#####.#.#
+#.#.#.......#.#
+#.#.#####.###.#
+#...........#.#
+###.#.#####.#.#
+#...#.....#.#.#
+#.#.#.###.#.#.#
+#.....#...#.#.#
+#.###.#.#.#.#.#
+#S..#.....#...#
+###############";
+
+    fn map(maze: &str) -> Grid<Entity> {
+        parse_map(&maze.lines().map(String::from).collect_vec()).unwrap()
+    }
+
+    #[test]
+    fn example_scores() {
+        let world = map(EXAMPLE);
+        let (score, tiles) = find_shortest_path(&world, &CostModel::default()).unwrap();
+        assert_eq!((score, tiles.len()), (7036, 45));
+
+        // Without turn costs a shorter, twistier path than the 36 steps of
+        // the best one wins
+        let costs = parse_costs("1,0").unwrap();
+        let (steps, _) = find_shortest_path(&world, &costs).unwrap();
+        assert_eq!(steps, 28);
+    }
+
+    #[test]
+    fn u_turn_cost() {
+        let world = map("#####\n#E.S#\n#####");
+        let u_turn = parse_costs("1,1000,10").unwrap();
+        for (costs, expected) in [(CostModel::default(), 2002), (u_turn, 12)] {
+            let (score, tiles) = find_shortest_path(&world, &costs).unwrap();
+            assert_eq!((score, tiles.len()), (expected, 3));
+        }
+    }
+
+    #[test]
+    fn graph_matches_grid_search() {
+        let world = map(EXAMPLE);
+        let graph = MazeGraph::build(&world);
+        assert!(graph.node_count() < world.iter().filter(|e| **e != Entity::Wall).count());
+
+        for costs in ["1,1000", "1,0", "5,3,1", "1,1000,1500"] {
+            let costs = parse_costs(costs).unwrap();
+            assert_eq!(graph.solve(&costs), find_shortest_path(&world, &costs));
+        }
+    }
+
+    #[test]
+    fn heuristic_is_admissible() {
+        let world = map(EXAMPLE);
+        let (start, end) = (
+            find_entity(&world, Entity::Start).unwrap(),
+            find_entity(&world, Entity::End).unwrap(),
+        );
+
+        for costs in ["1,1000", "1,0", "5,3,1", "1,1000,1500"] {
+            let costs = parse_costs(costs).unwrap();
+            let (score, _) = find_shortest_path(&world, &costs).unwrap();
+            assert!(costs.heuristic(start, Direction::Right, end) <= score);
+        }
     }
 }
@@ -0,0 +1,158 @@
+use bitvec::{order::Lsb0, slice::BitSlice};
+
+use crate::{u8_from_3bit_value, Opcode, ThreeBitError};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub operand: u8,
+}
+
+/// Decodes the packed program once, so running it no longer needs to go
+/// through `Opcode::try_from(&BitSlice)` on every step. Jump targets and
+/// combo operands are validated here so the fast paths never have to.
+pub fn decode(program: &BitSlice<u8, Lsb0>) -> Result<Vec<Instruction>, ThreeBitError> {
+    let instructions = program
+        .chunks(6)
+        .map(|chunk| {
+            if chunk.len() != 6 {
+                return Err(ThreeBitError::InvalidBitCount);
+            }
+            Ok(Instruction {
+                opcode: chunk[0..3].try_into()?,
+                operand: u8_from_3bit_value(&chunk[3..6])?,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for &Instruction { opcode, operand } in instructions.iter() {
+        match opcode {
+            // Jump operands address 3-bit words, every instruction is two words long.
+            Opcode::JNZ if operand % 2 != 0 || operand as usize / 2 >= instructions.len() => {
+                return Err(ThreeBitError::InvalidJumpTarget(operand))
+            }
+            Opcode::ADV | Opcode::BST | Opcode::OUT | Opcode::BDV | Opcode::CDV if operand == 7 => {
+                return Err(ThreeBitError::ReservedComboOperand)
+            }
+            _ => {}
+        }
+    }
+
+    Ok(instructions)
+}
+
+#[inline(always)]
+fn combo(registers: &[usize; 3], operand: u8) -> usize {
+    match operand {
+        0..=3 => operand as usize,
+        4 => registers[0],
+        5 => registers[1],
+        6 => registers[2],
+        _ => unreachable!(),
+    }
+}
+
+#[inline(always)]
+fn shift(a: usize, combo: usize) -> usize {
+    u32::try_from(combo)
+        .ok()
+        .and_then(|c| a.checked_shr(c))
+        .unwrap_or(0)
+}
+
+/// Runs an already decoded program with register A set to `a`.
+pub fn run_decoded(instructions: &[Instruction], a: usize, output: &mut Vec<u8>) {
+    let mut registers = [a, 0, 0];
+    let mut pc = 0;
+
+    while let Some(Instruction { opcode, operand }) = instructions.get(pc).copied() {
+        pc += 1;
+        match opcode {
+            Opcode::ADV => registers[0] = shift(registers[0], combo(&registers, operand)),
+            Opcode::BXL => registers[1] ^= operand as usize,
+            Opcode::BST => registers[1] = combo(&registers, operand) & 0b111,
+            Opcode::JNZ => {
+                if registers[0] != 0 {
+                    pc = operand as usize / 2;
+                }
+            }
+            Opcode::BXC => registers[1] ^= registers[2],
+            Opcode::OUT => output.push((combo(&registers, operand) & 0b111) as u8),
+            Opcode::BDV => registers[1] = shift(registers[0], combo(&registers, operand)),
+            Opcode::CDV => registers[2] = shift(registers[0], combo(&registers, operand)) & 0b111,
+        }
+    }
+}
+
+// Compiled programs keep the literal combo operands 0-3 in front of the
+// registers, so every combo operand is a plain index into this array.
+const A: usize = 4;
+const B: usize = 5;
+const C: usize = 6;
+
+type State = [usize; 7];
+type Step = Box<dyn Fn(&mut State, &mut Vec<u8>) -> usize>;
+
+/// A program compiled into one boxed closure per instruction. Each closure
+/// has its operand baked in and returns the index of the next instruction.
+pub struct CompiledProgram {
+    steps: Vec<Step>,
+}
+
+impl CompiledProgram {
+    pub fn compile(instructions: &[Instruction]) -> Self {
+        let steps = instructions
+            .iter()
+            .enumerate()
+            .map(|(idx, &Instruction { opcode, operand })| -> Step {
+                let next = idx + 1;
+                let src = operand as usize;
+                match opcode {
+                    Opcode::ADV => Box::new(move |r, _| {
+                        r[A] = shift(r[A], r[src]);
+                        next
+                    }),
+                    Opcode::BXL => Box::new(move |r, _| {
+                        r[B] ^= src;
+                        next
+                    }),
+                    Opcode::BST => Box::new(move |r, _| {
+                        r[B] = r[src] & 0b111;
+                        next
+                    }),
+                    Opcode::JNZ => {
+                        let target = src / 2;
+                        Box::new(move |r, _| if r[A] != 0 { target } else { next })
+                    }
+                    Opcode::BXC => Box::new(move |r, _| {
+                        r[B] ^= r[C];
+                        next
+                    }),
+                    Opcode::OUT => Box::new(move |r, out| {
+                        out.push((r[src] & 0b111) as u8);
+                        next
+                    }),
+                    Opcode::BDV => Box::new(move |r, _| {
+                        r[B] = shift(r[A], r[src]);
+                        next
+                    }),
+                    Opcode::CDV => Box::new(move |r, _| {
+                        r[C] = shift(r[A], r[src]) & 0b111;
+                        next
+                    }),
+                }
+            })
+            .collect();
+
+        Self { steps }
+    }
+
+    pub fn run(&self, a: usize, output: &mut Vec<u8>) {
+        let mut state = [0, 1, 2, 3, a, 0, 0];
+        let mut pc = 0;
+
+        while let Some(step) = self.steps.get(pc) {
+            pc = step(&mut state, output);
+        }
+    }
+}
@@ -1,8 +1,11 @@
-use std::sync::LazyLock;
+mod compile;
+
+use std::{sync::LazyLock, time::Instant};
 
 use bitvec::{bitvec, order::Lsb0, slice::BitSlice, vec::BitVec};
 
 use aoc_2024::input::read_lines;
+use compile::CompiledProgram;
 use itertools::Itertools;
 use regex::Regex;
 
@@ -32,6 +35,10 @@ enum Opcode {
 pub enum ThreeBitError {
     #[error("Invalid bit count for op code")]
     InvalidBitCount,
+    #[error("Invalid jump target {0}")]
+    InvalidJumpTarget(u8),
+    #[error("Reserved combo operand 7")]
+    ReservedComboOperand,
 }
 
 impl TryFrom<&BitSlice<u8>> for Opcode {
@@ -146,6 +153,10 @@ impl Computer {
         self.pc += 6;
     }
 
+    pub fn program(&self) -> &BitSlice<u8, Lsb0> {
+        &self.program
+    }
+
     pub fn get_output(&self) -> &[u8] {
         &self.output
     }
@@ -247,10 +258,70 @@ fn pseudo_computer(mut a: usize, output: &mut Vec<u8>) {
     }
 }
 
+fn benchmark(computer: &mut Computer, count: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let instructions = compile::decode(computer.program())?;
+    let compiled = CompiledProgram::compile(&instructions);
+    let mut output = Vec::with_capacity(32);
+
+    let report = |name: &str, start: Instant, checksum: usize| {
+        let elapsed = start.elapsed();
+        println!(
+            "{:<12} {:>10.2?} {:>14.0} A/s (checksum {})",
+            name,
+            elapsed,
+            count as f64 / elapsed.as_secs_f64(),
+            checksum
+        );
+    };
+
+    let start = Instant::now();
+    let mut checksum = 0usize;
+    for a in 0..count {
+        computer.reset(a);
+        while !computer.run()? {}
+        checksum += computer
+            .get_output()
+            .iter()
+            .map(|&v| v as usize)
+            .sum::<usize>();
+    }
+    report("Interpreted", start, checksum);
+
+    let start = Instant::now();
+    let mut checksum = 0usize;
+    for a in 0..count {
+        output.clear();
+        compile::run_decoded(&instructions, a, &mut output);
+        checksum += output.iter().map(|&v| v as usize).sum::<usize>();
+    }
+    report("Decoded", start, checksum);
+
+    let start = Instant::now();
+    let mut checksum = 0usize;
+    for a in 0..count {
+        output.clear();
+        compiled.run(a, &mut output);
+        checksum += output.iter().map(|&v| v as usize).sum::<usize>();
+    }
+    report("Compiled", start, checksum);
+
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let lines = read_lines("./inputs/day17/input.txt")?;
     let mut computer = parse_input(lines.flatten().into_iter())?;
 
+    let mut args = std::env::args().skip_while(|arg| arg != "--bench");
+    if args.next().is_some() {
+        let count = args
+            .next()
+            .map(|n| n.parse())
+            .transpose()?
+            .unwrap_or(1_000_000);
+        return benchmark(&mut computer, count);
+    }
+
     while !computer.run()? {}
 
     println!(
@@ -315,6 +386,46 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
 #[cfg(test)]
 mod test {
+    use super::*;
+
+    fn run_all(computer: &mut Computer, a: usize) -> Vec<u8> {
+        computer.reset(a);
+        while !computer.run().unwrap() {}
+        computer.get_output().to_vec()
+    }
+
+    #[test]
+    fn compiled_matches_interpreter() {
+        for input in ["./inputs/day17/test.txt", "./inputs/day17/input.txt"] {
+            let lines = read_lines(input).unwrap();
+            let mut computer = parse_input(lines.map_while(Result::ok)).unwrap();
+            let instructions = compile::decode(computer.program()).unwrap();
+            let compiled = CompiledProgram::compile(&instructions);
+
+            for a in (0..4096).chain([729, 35200350]) {
+                let expected = run_all(&mut computer, a);
+
+                let mut decoded = vec![];
+                compile::run_decoded(&instructions, a, &mut decoded);
+                assert_eq!(decoded, expected);
+
+                let mut output = vec![];
+                compiled.run(a, &mut output);
+                assert_eq!(output, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn compiled_example_output() {
+        let lines = read_lines("./inputs/day17/test.txt").unwrap();
+        let computer = parse_input(lines.map_while(Result::ok)).unwrap();
+        let compiled = CompiledProgram::compile(&compile::decode(computer.program()).unwrap());
+
+        let mut output = vec![];
+        compiled.run(729, &mut output);
+        assert_eq!(output, [4, 6, 3, 5, 6, 3, 5, 2, 1, 0]);
+    }
 
     #[test]
     fn diff_a() {
@@ -1,7 +1,11 @@
-use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::{
+    collections::{BinaryHeap, HashMap, HashSet},
+    error::Error,
+};
 
 use aoc_2024::input::read_lines;
 use itertools::Itertools;
+
 #[derive(PartialEq, Eq, Clone, Copy)]
 struct PathElement {
     pub score: usize,
@@ -23,12 +27,92 @@ impl PartialOrd for PathElement {
     }
 }
 
+/// Size of the memory space, bytes fallen before the first search and the
+/// endpoints. Coordinates are `(x, y)`, `bounds` is `(width, height)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Params {
+    pub bounds: (usize, usize),
+    pub num_bytes: usize,
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+impl Params {
+    fn new(bounds: (usize, usize), num_bytes: usize) -> Self {
+        Self {
+            bounds,
+            num_bytes,
+            start: (0, 0),
+            end: (bounds.0 - 1, bounds.1 - 1),
+        }
+    }
+
+    fn contains(&self, (x, y): (usize, usize)) -> bool {
+        x < self.bounds.0 && y < self.bounds.1
+    }
+}
+
+fn neighbours((x, y): (usize, usize), bounds: (usize, usize)) -> Vec<(usize, usize)> {
+    let mut options = vec![];
+    if x > 0 {
+        options.push((x - 1, y));
+    }
+    if x + 1 < bounds.0 {
+        options.push((x + 1, y));
+    }
+    if y > 0 {
+        options.push((x, y - 1));
+    }
+    if y + 1 < bounds.1 {
+        options.push((x, y + 1));
+    }
+    options
+}
+
 fn find_shortest_path(
     start: (usize, usize),
     end: (usize, usize),
     bounds: (usize, usize),
     fallen_bytes: &HashSet<(usize, usize)>,
 ) -> Option<usize> {
+    find_shortest_path_by(start, end, bounds, 0, |pos, _| fallen_bytes.contains(&pos))
+}
+
+/// Searches over `(pos, t)`, starting at `start_time` and taking one tick
+/// per step. The k-th byte (counting from 1) lands at time k, and a cell is
+/// blocked only once its byte has fallen.
+fn find_escape_path(
+    start: (usize, usize),
+    end: (usize, usize),
+    bounds: (usize, usize),
+    bytes: &[(usize, usize)],
+    start_time: usize,
+) -> Option<usize> {
+    let mut fall_times: HashMap<(usize, usize), usize> = HashMap::new();
+    for (k, &pos) in bytes.iter().enumerate() {
+        fall_times.entry(pos).or_insert(k + 1);
+    }
+
+    find_shortest_path_by(start, end, bounds, start_time, |pos, t| {
+        fall_times.get(&pos).is_some_and(|&fall| fall <= t)
+    })
+}
+
+/// Number of steps from `start` to `end`, where `blocked(pos, t)` tells if
+/// `pos` can't be entered at time `t`. Blocked cells must stay blocked, so
+/// reaching a cell earlier is never worse and the best time per cell is
+/// enough to search the `(pos, t)` states.
+fn find_shortest_path_by(
+    start: (usize, usize),
+    end: (usize, usize),
+    bounds: (usize, usize),
+    start_time: usize,
+    blocked: impl Fn((usize, usize), usize) -> bool,
+) -> Option<usize> {
+    if blocked(start, start_time) {
+        return None;
+    }
+
     let mut q: BinaryHeap<PathElement> = BinaryHeap::new();
 
     let mut distances: HashMap<(usize, usize), usize> = HashMap::default();
@@ -39,60 +123,14 @@ fn find_shortest_path(
     });
     distances.insert(start, 0);
 
-    let res = loop {
-        if q.is_empty() {
-            break None;
-        }
-        let path_elem = q.pop().unwrap();
-
-        let (x, y) = path_elem.pos;
-
-        if (x, y) == end {
-            break Some(path_elem.score);
+    while let Some(path_elem) = q.pop() {
+        if path_elem.pos == end {
+            return Some(path_elem.score);
         }
 
-        if x > 0 {
-            let option = (x - 1, y);
-            if !fallen_bytes.contains(&option)
-                && path_elem.score + 1 < *distances.get(&option).unwrap_or(&usize::MAX)
-            {
-                let mut new_elem = path_elem;
-                new_elem.score += 1;
-                new_elem.pos = option;
-
-                distances.insert(option, new_elem.score);
-                q.push(new_elem);
-            }
-        }
-        if x < bounds.0 - 1 {
-            let option = (x + 1, y);
-            if !fallen_bytes.contains(&option)
-                && path_elem.score + 1 < *distances.get(&option).unwrap_or(&usize::MAX)
-            {
-                let mut new_elem = path_elem;
-                new_elem.score += 1;
-                new_elem.pos = option;
-
-                distances.insert(option, new_elem.score);
-                q.push(new_elem);
-            }
-        }
-        if y > 0 {
-            let option = (x, y - 1);
-            if !fallen_bytes.contains(&option)
-                && path_elem.score + 1 < *distances.get(&option).unwrap_or(&usize::MAX)
-            {
-                let mut new_elem = path_elem;
-                new_elem.score += 1;
-                new_elem.pos = option;
-
-                distances.insert(option, new_elem.score);
-                q.push(new_elem);
-            }
-        }
-        if y < bounds.0 - 1 {
-            let option = (x, y + 1);
-            if !fallen_bytes.contains(&option)
+        let t = start_time + path_elem.score + 1;
+        for option in neighbours(path_elem.pos, bounds) {
+            if !blocked(option, t)
                 && path_elem.score + 1 < *distances.get(&option).unwrap_or(&usize::MAX)
             {
                 let mut new_elem = path_elem;
@@ -103,9 +141,9 @@ fn find_shortest_path(
                 q.push(new_elem);
             }
         }
-    };
+    }
 
-    res
+    None
 }
 
 fn print_map(
@@ -113,13 +151,13 @@ fn print_map(
     visited: &HashMap<(usize, usize), usize>,
     bounds: (usize, usize),
 ) {
-    for i in 0..bounds.0 {
+    for y in 0..bounds.1 {
         println!(
             "{}",
-            (0..bounds.1)
-                .map(|j| if fallen_bytes.contains(&(i, j)) {
+            (0..bounds.0)
+                .map(|x| if fallen_bytes.contains(&(x, y)) {
                     "#"
-                } else if visited.contains_key(&(i, j)) {
+                } else if visited.contains_key(&(x, y)) {
                     "O"
                 } else {
                     "."
@@ -127,62 +165,206 @@ fn print_map(
                 .join("")
         )
     }
-    println!("");
+    println!();
+}
+
+/// Parses two numbers separated by `sep`, e.g. `X,Y` or `WxH`.
+fn parse_pair(arg: &str, sep: char) -> Result<(usize, usize), Box<dyn Error>> {
+    let (a, b) = arg
+        .split_once(sep)
+        .ok_or_else(|| format!("expected two numbers separated by {:?}, got {:?}", sep, arg))?;
+    Ok((a.trim().parse()?, b.trim().parse()?))
+}
+
+/// Reads the input path and parameters from the command line:
+/// `--example` for the 7x7 example, `--input PATH`, `--size WxH`,
+/// `--bytes N`, `--start X,Y` and `--end X,Y`. The end defaults to the
+/// bottom right corner.
+fn parse_args(args: &[String]) -> Result<(String, Params), Box<dyn Error>> {
+    let flag = |name: &str| -> Result<Option<&str>, Box<dyn Error>> {
+        match args.iter().position(|arg| arg == name) {
+            Some(i) => Ok(Some(
+                args.get(i + 1)
+                    .ok_or_else(|| format!("{} needs a value", name))?,
+            )),
+            None => Ok(None),
+        }
+    };
+
+    let example = args.iter().any(|arg| arg == "--example");
+    let (mut input, mut params) = if example {
+        (
+            "./inputs/day18/test.txt".to_string(),
+            Params::new((7, 7), 12),
+        )
+    } else {
+        (
+            "./inputs/day18/input.txt".to_string(),
+            Params::new((71, 71), 1024),
+        )
+    };
+
+    if let Some(path) = flag("--input")? {
+        input = path.to_string();
+    }
+    if let Some(size) = flag("--size")? {
+        let bounds = parse_pair(size, 'x')?;
+        if bounds.0 == 0 || bounds.1 == 0 {
+            return Err(format!("empty memory space {:?}", size).into());
+        }
+        params = Params::new(bounds, params.num_bytes);
+    }
+    if let Some(bytes) = flag("--bytes")? {
+        params.num_bytes = bytes.parse()?;
+    }
+    if let Some(start) = flag("--start")? {
+        params.start = parse_pair(start, ',')?;
+    }
+    if let Some(end) = flag("--end")? {
+        params.end = parse_pair(end, ',')?;
+    }
+
+    for pos in [params.start, params.end] {
+        if !params.contains(pos) {
+            return Err(format!("{:?} is outside of {:?}", pos, params.bounds).into());
+        }
+    }
+
+    Ok((input, params))
 }
 
-fn main() {
-    if let Ok(lines) = read_lines("./inputs/day18/input.txt") {
-        // Parsing
-        const BOUNDS: (usize, usize) = (71, 71);
-        const START: (usize, usize) = (0, 0);
-        const END: (usize, usize) = (BOUNDS.0 - 1, BOUNDS.1 - 1);
-        const NUM_BYTES_FALLEN: usize = 1024;
-        let line_vec = lines.flatten().into_iter().collect_vec();
-        let mut fallen_bytes = line_vec[0..NUM_BYTES_FALLEN]
-            .iter()
-            .map(|line| {
-                let mut it = line.split(",");
-                let (x, y) = (
-                    it.next()
-                        .expect("no x coord")
-                        .parse::<usize>()
-                        .expect("x coord isnt uint"),
-                    it.next()
-                        .expect("no y coord")
-                        .parse::<usize>()
-                        .expect("y coord isnt uint"),
-                );
-                assert!(it.next().is_none());
-                (x, y)
-            })
-            .collect::<HashSet<_>>();
-
-        print_map(&fallen_bytes, &HashMap::new(), BOUNDS);
-
-        let score = find_shortest_path(START, END, BOUNDS, &fallen_bytes).unwrap();
-
-        println!("Score: {}", score);
-
-        for line in line_vec[NUM_BYTES_FALLEN..].iter() {
-            println!("{}", line);
-            let mut it = line.split(",");
-            let (x, y) = (
-                it.next()
-                    .expect("no x coord")
-                    .parse::<usize>()
-                    .expect("x coord isnt uint"),
-                it.next()
-                    .expect("no y coord")
-                    .parse::<usize>()
-                    .expect("y coord isnt uint"),
-            );
-            assert!(it.next().is_none());
-            fallen_bytes.insert((x, y));
-
-            if find_shortest_path(START, END, BOUNDS, &fallen_bytes).is_none() {
-                println!("First block {:?}", (x, y));
-                break;
+fn parse_bytes(lines: &[String], params: &Params) -> Result<Vec<(usize, usize)>, Box<dyn Error>> {
+    lines
+        .iter()
+        .map(|line| {
+            let pos = parse_pair(line, ',')?;
+            if !params.contains(pos) {
+                return Err(format!("byte {:?} is outside of {:?}", pos, params.bounds).into());
             }
+            Ok(pos)
+        })
+        .collect()
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let (input, params) = parse_args(&std::env::args().skip(1).collect_vec())?;
+
+    let lines = read_lines(&input).map_err(|e| format!("can't read {}: {}", input, e))?;
+
+    // Parsing
+    let line_vec = lines.map_while(Result::ok).collect_vec();
+    let bytes = parse_bytes(&line_vec, &params)?;
+    if bytes.len() < params.num_bytes {
+        return Err(format!("only {} bytes in {}", bytes.len(), input).into());
+    }
+
+    let mut fallen_bytes = bytes[0..params.num_bytes]
+        .iter()
+        .copied()
+        .collect::<HashSet<_>>();
+
+    print_map(&fallen_bytes, &HashMap::new(), params.bounds);
+
+    match find_shortest_path(params.start, params.end, params.bounds, &fallen_bytes) {
+        Some(score) => println!("Score: {}", score),
+        None => println!("No path after {} bytes", params.num_bytes),
+    }
+
+    match find_escape_path(
+        params.start,
+        params.end,
+        params.bounds,
+        &bytes,
+        params.num_bytes,
+    ) {
+        Some(steps) => println!("Escape while bytes fall: {}", steps),
+        None => println!("No escape while bytes fall"),
+    }
+
+    for &(x, y) in bytes[params.num_bytes..].iter() {
+        println!("{},{}", x, y);
+        fallen_bytes.insert((x, y));
+
+        if find_shortest_path(params.start, params.end, params.bounds, &fallen_bytes).is_none() {
+            println!("First block {:?}", (x, y));
+            break;
         }
     }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn example() {
+        let (input, params) = parse_args(&["--example".to_string()]).unwrap();
+        assert_eq!(params, Params::new((7, 7), 12));
+        let lines = read_lines(&input)
+            .unwrap()
+            .map_while(Result::ok)
+            .collect_vec();
+        let bytes = parse_bytes(&lines, &params).unwrap();
+        let fallen = bytes[..12].iter().copied().collect::<HashSet<_>>();
+
+        assert_eq!(
+            find_shortest_path(params.start, params.end, params.bounds, &fallen),
+            Some(22)
+        );
+    }
+
+    #[test]
+    fn non_square_space() {
+        // 6 wide, 2 high: a wall across x = 3 with a gap only at the bottom
+        let fallen = HashSet::from([(3, 0)]);
+        let params = Params::new((6, 2), 0);
+        assert_eq!(params.end, (5, 1));
+        assert_eq!(
+            find_shortest_path(params.start, params.end, params.bounds, &fallen),
+            Some(6)
+        );
+
+        let blocked = HashSet::from([(3, 0), (3, 1)]);
+        assert_eq!(
+            find_shortest_path(params.start, params.end, params.bounds, &blocked),
+            None
+        );
+    }
+
+    #[test]
+    fn bytes_fall_while_walking() {
+        // A corridor along y = 0 whose far end collapses at time 3
+        let bytes = [(0, 1), (1, 1), (3, 0), (2, 1), (3, 1)];
+        let bounds = (4, 2);
+
+        // Three steps to the end would arrive at time 3, one tick too late
+        assert_eq!(find_escape_path((0, 0), (3, 0), bounds, &bytes, 0), None);
+        // Starting one cell closer beats the collapse
+        assert_eq!(find_escape_path((1, 0), (3, 0), bounds, &bytes, 0), Some(2));
+        // The row below is walled off once every byte has fallen, but each
+        // of its cells is passed before its byte lands
+        assert_eq!(find_escape_path((0, 1), (3, 1), bounds, &bytes, 0), Some(3));
+        let fallen = bytes.iter().copied().collect::<HashSet<_>>();
+        assert_eq!(find_shortest_path((0, 1), (3, 1), bounds, &fallen), None);
+    }
+
+    #[test]
+    fn arguments() {
+        let args = ["--size", "10x4", "--bytes", "3", "--start", "1,2"].map(String::from);
+        let (_, params) = parse_args(&args).unwrap();
+        assert_eq!(
+            params,
+            Params {
+                bounds: (10, 4),
+                num_bytes: 3,
+                start: (1, 2),
+                end: (9, 3)
+            }
+        );
+
+        let args = ["--size", "10x4", "--end", "3,9"].map(String::from);
+        assert!(parse_args(&args).is_err());
+    }
 }
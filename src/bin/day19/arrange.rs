@@ -0,0 +1,228 @@
+use crate::trie::TowelTrie;
+
+/// The concrete arrangements of one pattern. Arrangements are ordered
+/// lexicographically as sequences of towels; all towels that fit at a
+/// position are prefixes of the same text, so that is shortest towel first.
+pub struct Arrangements<'t, 'a> {
+    trie: &'t TowelTrie<'a>,
+    pattern: &'t [u8],
+    /// Ways to finish the pattern from each position, saturating. A
+    /// saturated count is still larger than any rank we can be asked for.
+    ways: Vec<u128>,
+}
+
+impl<'t, 'a> Arrangements<'t, 'a> {
+    pub fn new(trie: &'t TowelTrie<'a>, pattern: &'t str) -> Self {
+        let pattern = pattern.as_bytes();
+        let mut ways = vec![0u128; pattern.len() + 1];
+        ways[pattern.len()] = 1;
+
+        for i in (0..pattern.len()).rev() {
+            ways[i] = trie
+                .prefixes(&pattern[i..])
+                .fold(0, |sum, (len, _)| sum.saturating_add(ways[i + len]));
+        }
+
+        Self {
+            trie,
+            pattern,
+            ways,
+        }
+    }
+
+    /// Number of arrangements, saturating at `u128::MAX`.
+    pub fn count(&self) -> u128 {
+        self.ways[0]
+    }
+
+    /// Towels that fit at `pos` and still leave a way to finish, as
+    /// `(length, towel index)`.
+    fn choices(&self, pos: usize) -> Vec<(usize, usize)> {
+        self.trie
+            .prefixes(&self.pattern[pos..])
+            .filter(|(len, _)| self.ways[pos + len] > 0)
+            .collect()
+    }
+
+    /// All arrangements in lexicographic order, produced one at a time.
+    pub fn iter(&self) -> Iter<'_, 't, 'a> {
+        let stack = if self.count() > 0 {
+            vec![Frame {
+                pos: 0,
+                choices: self.choices(0),
+                next: 0,
+            }]
+        } else {
+            vec![]
+        };
+
+        Iter {
+            arrangements: self,
+            stack,
+            path: vec![],
+        }
+    }
+
+    /// The arrangement at zero-based rank `k` in lexicographic order,
+    /// without producing the ones before it.
+    pub fn nth(&self, mut k: u128) -> Option<Vec<&'a str>> {
+        if k >= self.count() {
+            return None;
+        }
+
+        let mut arrangement = vec![];
+        let mut pos = 0;
+        while pos < self.pattern.len() {
+            for (len, towel) in self.choices(pos) {
+                let ways = self.ways[pos + len];
+                if k < ways {
+                    arrangement.push(self.trie.towel(towel));
+                    pos += len;
+                    break;
+                }
+                k -= ways;
+            }
+        }
+
+        Some(arrangement)
+    }
+
+    /// An arrangement with the fewest towels, the lexicographically first
+    /// one if there are several.
+    pub fn fewest_towels(&self) -> Option<Vec<&'a str>> {
+        let n = self.pattern.len();
+        // Fewest towels to finish from each position and the first towel
+        let mut best: Vec<Option<(usize, usize, usize)>> = vec![None; n + 1];
+        best[n] = Some((0, 0, 0));
+
+        for i in (0..n).rev() {
+            for (len, towel) in self.trie.prefixes(&self.pattern[i..]) {
+                if let Some((count, _, _)) = best[i + len] {
+                    if best[i].is_none_or(|(fewest, _, _)| count + 1 < fewest) {
+                        best[i] = Some((count + 1, len, towel));
+                    }
+                }
+            }
+        }
+
+        best[0]?;
+        let mut arrangement = vec![];
+        let mut pos = 0;
+        while pos < n {
+            // Every first towel leads to a position that can be finished
+            let (_, len, towel) = best[pos].unwrap();
+            arrangement.push(self.trie.towel(towel));
+            pos += len;
+        }
+
+        Some(arrangement)
+    }
+}
+
+struct Frame {
+    pos: usize,
+    choices: Vec<(usize, usize)>,
+    next: usize,
+}
+
+/// Depth-first walk over the arrangements. Dead ends are never entered,
+/// so every step down leads to at least one arrangement.
+pub struct Iter<'r, 't, 'a> {
+    arrangements: &'r Arrangements<'t, 'a>,
+    stack: Vec<Frame>,
+    /// Towels placed so far, one for every frame but the first.
+    path: Vec<&'a str>,
+}
+
+impl<'a> Iterator for Iter<'_, '_, 'a> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let end = self.arrangements.pattern.len();
+
+        loop {
+            let frame = self.stack.last_mut()?;
+
+            if frame.pos == end {
+                let arrangement = self.path.clone();
+                self.stack.pop();
+                self.path.pop();
+                return Some(arrangement);
+            }
+
+            match frame.choices.get(frame.next) {
+                Some(&(len, towel)) => {
+                    frame.next += 1;
+                    let pos = frame.pos + len;
+                    self.path.push(self.arrangements.trie.towel(towel));
+                    self.stack.push(Frame {
+                        pos,
+                        choices: self.arrangements.choices(pos),
+                        next: 0,
+                    });
+                }
+                None => {
+                    self.stack.pop();
+                    self.path.pop();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const TOWELS: [&str; 8] = ["r", "wr", "b", "g", "bwu", "rb", "gb", "br"];
+
+    #[test]
+    fn enumerate_in_order() {
+        let trie = TowelTrie::new(&TOWELS);
+        let arrangements = Arrangements::new(&trie, "gbbr");
+
+        let all = arrangements.iter().collect::<Vec<_>>();
+        assert_eq!(
+            all,
+            vec![
+                vec!["g", "b", "b", "r"],
+                vec!["g", "b", "br"],
+                vec!["gb", "b", "r"],
+                vec!["gb", "br"],
+            ]
+        );
+        assert_eq!(all.len() as u128, arrangements.count());
+        for (k, arrangement) in all.iter().enumerate() {
+            assert_eq!(arrangements.nth(k as u128).as_ref(), Some(arrangement));
+        }
+        assert_eq!(arrangements.nth(4), None);
+        assert_eq!(arrangements.fewest_towels(), Some(vec!["gb", "br"]));
+    }
+
+    #[test]
+    fn impossible_and_empty_patterns() {
+        let trie = TowelTrie::new(&TOWELS);
+
+        let impossible = Arrangements::new(&trie, "ubwu");
+        assert_eq!(impossible.iter().next(), None);
+        assert_eq!(impossible.nth(0), None);
+        assert_eq!(impossible.fewest_towels(), None);
+
+        let empty = Arrangements::new(&trie, "");
+        assert_eq!(empty.iter().collect::<Vec<_>>(), vec![Vec::<&str>::new()]);
+        assert_eq!(empty.fewest_towels(), Some(vec![]));
+    }
+
+    #[test]
+    fn rank_huge_counts() {
+        let trie = TowelTrie::new(&["r", "rr"]);
+        let pattern = "r".repeat(200);
+        let arrangements = Arrangements::new(&trie, &pattern);
+
+        // Fibonacci numbers overflow u128 long before 200
+        assert_eq!(arrangements.count(), u128::MAX);
+        assert_eq!(arrangements.nth(0), Some(vec!["r"; 200]));
+        assert_eq!(arrangements.fewest_towels(), Some(vec!["rr"; 100]));
+        assert_eq!(arrangements.iter().nth(1), arrangements.nth(1));
+    }
+}
@@ -0,0 +1,96 @@
+use std::fmt::Display;
+
+use aoc_2024::bigint::BigUint;
+
+/// A number type arrangements can be counted in. Additions report overflow
+/// instead of wrapping.
+pub trait Count: Clone {
+    /// Zero of the same kind as `self`, e.g. with the same modulus.
+    fn zero(&self) -> Self;
+
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+}
+
+/// Whether there is any arrangement at all, never overflows.
+impl Count for bool {
+    fn zero(&self) -> Self {
+        false
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(*self || *other)
+    }
+}
+
+impl Count for usize {
+    fn zero(&self) -> Self {
+        0
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        usize::checked_add(*self, *other)
+    }
+}
+
+impl Count for u128 {
+    fn zero(&self) -> Self {
+        0
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        u128::checked_add(*self, *other)
+    }
+}
+
+impl Count for BigUint {
+    fn zero(&self) -> Self {
+        BigUint::zero()
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+}
+
+/// A count modulo `modulus`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Modular {
+    pub value: u64,
+    pub modulus: u64,
+}
+
+impl Modular {
+    pub fn one(modulus: u64) -> Self {
+        assert!(modulus > 0);
+
+        Self {
+            value: 1 % modulus,
+            modulus,
+        }
+    }
+}
+
+impl Count for Modular {
+    fn zero(&self) -> Self {
+        Self {
+            value: 0,
+            modulus: self.modulus,
+        }
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        assert_eq!(self.modulus, other.modulus);
+        let sum = (self.value as u128 + other.value as u128) % self.modulus as u128;
+
+        Some(Self {
+            value: sum as u64,
+            modulus: self.modulus,
+        })
+    }
+}
+
+impl Display for Modular {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (mod {})", self.value, self.modulus)
+    }
+}
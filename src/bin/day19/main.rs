@@ -1,59 +1,68 @@
-use aoc_2024::{input::read_lines, utils::Grid};
+mod arrange;
+mod count;
+mod trie;
+
+use std::fmt::Display;
+
+use aoc_2024::{bigint::BigUint, input::read_lines};
+use arrange::Arrangements;
+use count::{Count, Modular};
 use itertools::Itertools;
+use trie::TowelTrie;
 
-fn match_towels_in_pattern<'a>(
-    towels: &[&'a str],
-    pattern: &str,
-) -> Vec<(&'a str, (usize, usize))> {
-    let mut towel_matches = vec![];
-
-    for towel in towels {
-        let mut idx = 0;
-        // Each time a towel is found within the pattern, store (start, end)
-        // IMPORTANT: only increment 1 step since a towel match can overlap itself e.g.
-        // for a pattern 'rwrwr' towel 'rwr' matches (0,3) and (2, 5)
-        while let Some(first) = pattern[idx..].find(towel) {
-            towel_matches.push((*towel, (idx + first, idx + first + towel.len())));
-            idx += first + 1;
-            if idx >= pattern.len() {
-                break;
-            }
-        }
-    }
+fn is_pattern_possible(towels: &TowelTrie, pattern: &str) -> bool {
+    // A pattern is possible if there are more than zero ways to arrive at the end position
+    towels.count_arrangements(pattern, true) == Some(true)
+}
 
-    towel_matches
+/// Sum of the arrangement counts of all patterns in the type of `one`, or
+/// `None` if that overflows.
+fn count_all_arrangements<C: Count>(towels: &TowelTrie, patterns: &[&str], one: C) -> Option<C> {
+    patterns.iter().try_fold(one.zero(), |total, pattern| {
+        total.checked_add(&towels.count_arrangements(pattern, one.clone())?)
+    })
 }
-fn count_all_towel_arrangements_matrix(towels: &[&str], pattern: &str) -> usize {
-    // Find all substring matches of towels in the pattern
-    let towel_matches = match_towels_in_pattern(towels, pattern);
 
-    // Construct adjacency matrix, a 1 means there is a transition from posititon i to position j - (i, j) := (row_number, col_number)
-    let mut adj_matrix: Grid<u8> = Grid::zeros(pattern.len() + 1, pattern.len() + 1);
-    towel_matches
-        .iter()
-        .for_each(|(_, (i, j))| adj_matrix.set((*i, *j), 1));
-
-    // Calculate the different ways of arriving at pattern positions
-    let mut paths_to_rows = vec![0usize; pattern.len() + 1];
-    // There is exactly one way to arrive at the 0th position
-    paths_to_rows[0] = 1;
-
-    for i in 1..pattern.len() + 1 {
-        let mut paths_to_row = 0;
-        // The number of ways to arrive at this position is equal to the sum of the number of ways to arrive at any position leading to this one
-        for col in 0..i {
-            paths_to_row += (*adj_matrix.get((col, i)) as usize) * paths_to_rows[col];
-        }
+fn print_all_arrangements<C: Count + Display>(
+    towels: &TowelTrie,
+    patterns: &[&str],
+    one: C,
+    name: &str,
+) {
+    match count_all_arrangements(towels, patterns, one) {
+        Some(count) => println!("Possible arrangements: {}", count),
+        None => println!(
+            "Possible arrangements: overflows {}, try --count u128, big or mod:M",
+            name
+        ),
+    }
+}
 
-        paths_to_rows[i] = paths_to_row;
+/// Prints the count, the fewest-towel arrangement, the first few
+/// arrangements and, if given, the one at rank `k`.
+fn inspect(towels: &TowelTrie, pattern: &str, k: Option<u128>) {
+    let arrangements = Arrangements::new(towels, pattern);
+    match arrangements.count() {
+        u128::MAX => println!("{}: at least {} arrangements", pattern, u128::MAX),
+        count => println!("{}: {} arrangements", pattern, count),
     }
 
-    paths_to_rows[pattern.len()]
-}
+    let Some(fewest) = arrangements.fewest_towels() else {
+        return;
+    };
+    println!("Fewest towels ({}): {}", fewest.len(), fewest.join(" "));
 
-fn is_pattern_possible<'a>(towels: &[&'a str], pattern: &str) -> bool {
-    // A pattern is possible if there are more than zero ways to arrive at the end position
-    count_all_towel_arrangements_matrix(towels, pattern) > 0
+    println!("First arrangements:");
+    for arrangement in arrangements.iter().take(5) {
+        println!("  {}", arrangement.join(" "));
+    }
+
+    if let Some(k) = k {
+        match arrangements.nth(k) {
+            Some(arrangement) => println!("Arrangement {}: {}", k, arrangement.join(" ")),
+            None => println!("No arrangement {}", k),
+        }
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -67,20 +76,179 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     assert!(lines[1].is_empty());
     let patterns = lines[2..].iter().map(|s| s.as_str()).collect_vec();
+    let towels = TowelTrie::new(&towel_patterns);
+
+    // --inspect PATTERN [K]
+    let args = std::env::args().skip(1).collect_vec();
+    if args.first().is_some_and(|arg| arg == "--inspect") {
+        let pattern = args.get(1).ok_or("--inspect needs a PATTERN")?;
+        let k = args.get(2).map(|k| k.parse()).transpose()?;
+        inspect(&towels, pattern, k);
+        return Ok(());
+    }
 
     let possible_patterns = patterns
         .iter()
-        .filter(|p| is_pattern_possible(&towel_patterns, p))
+        .filter(|p| is_pattern_possible(&towels, p))
         .collect_vec();
 
     println!("Possible patterns: {}", possible_patterns.len());
 
-    let cnt = patterns
-        .iter()
-        .map(|p| count_all_towel_arrangements_matrix(&towel_patterns, p))
-        .sum::<usize>();
-
-    println!("Possible arrangements: {}", cnt);
+    // --count usize|u128|big|mod:M
+    let mode = match args.iter().position(|arg| arg == "--count") {
+        Some(i) => args.get(i + 1).ok_or("--count needs a mode")?.as_str(),
+        None => "usize",
+    };
+    match mode {
+        "usize" => print_all_arrangements(&towels, &patterns, 1usize, mode),
+        "u128" => print_all_arrangements(&towels, &patterns, 1u128, mode),
+        "big" => print_all_arrangements(&towels, &patterns, BigUint::from(1), mode),
+        _ => {
+            let modulus = mode
+                .strip_prefix("mod:")
+                .ok_or_else(|| format!("unknown count mode {:?}", mode))?
+                .parse()?;
+            if modulus == 0 {
+                return Err("modulus must not be zero".into());
+            }
+            print_all_arrangements(&towels, &patterns, Modular::one(modulus), mode);
+        }
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use aoc_2024::utils::Grid;
+
+    // Reference implementation: a dense adjacency matrix over pattern
+    // positions built from every towel occurrence.
+    fn match_towels_in_pattern<'a>(
+        towels: &[&'a str],
+        pattern: &str,
+    ) -> Vec<(&'a str, (usize, usize))> {
+        let mut towel_matches = vec![];
+
+        for towel in towels {
+            let mut idx = 0;
+            // Each time a towel is found within the pattern, store (start, end)
+            // IMPORTANT: only increment 1 step since a towel match can overlap itself e.g.
+            // for a pattern 'rwrwr' towel 'rwr' matches (0,3) and (2, 5)
+            while let Some(first) = pattern[idx..].find(towel) {
+                towel_matches.push((*towel, (idx + first, idx + first + towel.len())));
+                idx += first + 1;
+                if idx >= pattern.len() {
+                    break;
+                }
+            }
+        }
+
+        towel_matches
+    }
+
+    fn count_all_towel_arrangements_matrix(towels: &[&str], pattern: &str) -> usize {
+        // Find all substring matches of towels in the pattern
+        let towel_matches = match_towels_in_pattern(towels, pattern);
+
+        // Construct adjacency matrix, a 1 means there is a transition from posititon i to position j - (i, j) := (row_number, col_number)
+        let mut adj_matrix: Grid<u8> = Grid::zeros(pattern.len() + 1, pattern.len() + 1);
+        towel_matches
+            .iter()
+            .for_each(|(_, (i, j))| adj_matrix.set((*i, *j), 1));
+
+        // Calculate the different ways of arriving at pattern positions
+        let mut paths_to_rows = vec![0usize; pattern.len() + 1];
+        // There is exactly one way to arrive at the 0th position
+        paths_to_rows[0] = 1;
+
+        for i in 1..pattern.len() + 1 {
+            let mut paths_to_row = 0;
+            // The number of ways to arrive at this position is equal to the sum of the number of ways to arrive at any position leading to this one
+            for col in 0..i {
+                paths_to_row += (*adj_matrix.get((col, i)) as usize) * paths_to_rows[col];
+            }
+
+            paths_to_rows[i] = paths_to_row;
+        }
+
+        paths_to_rows[pattern.len()]
+    }
+
+    #[test]
+    fn trie_matches_matrix() {
+        let towels = ["r", "wr", "b", "g", "bwu", "rb", "gb", "br", "rr", "rrr"];
+        let patterns = [
+            "brwrr",
+            "bggr",
+            "gbbr",
+            "rrbgbr",
+            "ubwu",
+            "bwurrg",
+            "brgr",
+            "bbrgwb",
+            "rrrrrrrrrr",
+            "",
+            "rwrwr",
+        ];
+        let trie = TowelTrie::new(&towels);
+
+        for pattern in patterns {
+            assert_eq!(
+                trie.count_arrangements(pattern, 1usize),
+                Some(count_all_towel_arrangements_matrix(&towels, pattern)),
+                "{}",
+                pattern
+            );
+        }
+        assert_eq!(trie.count_arrangements("gbbr", 1usize), Some(4));
+        assert_eq!(trie.count_arrangements("ubwu", 1usize), Some(0));
+    }
+
+    #[test]
+    fn duplicate_and_empty_towels() {
+        let trie = TowelTrie::new(&["", "rw", "r", "rw", "w"]);
+        assert_eq!(trie.count_arrangements("rwr", 1usize), Some(2));
+        assert_eq!(trie.prefixes(b"rwr").collect_vec(), vec![(1, 1), (2, 0)]);
+    }
+
+    #[test]
+    fn overflow_aware_counting() {
+        let trie = TowelTrie::new(&["r", "rr", "rrr"]);
+        let tribonacci = |n: usize| {
+            let mut t = [1u128, 1, 2];
+            for _ in 2..n {
+                t = [t[1], t[2], t[0] + t[1] + t[2]];
+            }
+            t[n.min(2)]
+        };
+
+        // Tribonacci numbers leave u64 behind around n = 75
+        let pattern = "r".repeat(100);
+        let expected = tribonacci(100);
+        assert_eq!(trie.count_arrangements(&pattern, 1usize), None);
+        assert_eq!(trie.count_arrangements(&pattern, 1u128), Some(expected));
+        let big = trie.count_arrangements(&pattern, BigUint::from(1)).unwrap();
+        assert_eq!(big.to_u128(), Some(expected));
+        assert_eq!(big.to_string(), expected.to_string());
+        let modular = trie.count_arrangements(&pattern, Modular::one(1_000_000_007));
+        assert_eq!(modular.unwrap().value as u128, expected % 1_000_000_007);
+
+        // ... and u128 around n = 150
+        let pattern = "r".repeat(200);
+        assert_eq!(trie.count_arrangements(&pattern, 1u128), None);
+        let big = trie.count_arrangements(&pattern, BigUint::from(1)).unwrap();
+        assert_eq!(big.to_u128(), None);
+        let modular = trie.count_arrangements(&pattern, Modular::one(1_000_000_007));
+        assert_eq!(modular.unwrap().value, big.rem_u32(1_000_000_007) as u64);
+        assert_eq!(trie.count_arrangements(&pattern, true), Some(true));
+
+        let patterns = ["r".repeat(100), "r".repeat(100)];
+        let patterns = patterns.iter().map(|p| p.as_str()).collect_vec();
+        assert_eq!(
+            count_all_arrangements(&trie, &patterns, 1u128),
+            Some(2 * expected)
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use crate::count::Count;
+
+/// Prefix trie over the towels, one stripe colour per edge.
+pub struct TowelTrie<'a> {
+    towels: Vec<&'a str>,
+    nodes: Vec<Node>,
+}
+
+#[derive(Default)]
+struct Node {
+    /// Colours are few, so a short list beats a map.
+    children: Vec<(u8, usize)>,
+    /// Index into `towels` of the towel ending here.
+    towel: Option<usize>,
+}
+
+impl<'a> TowelTrie<'a> {
+    /// Builds the trie. Duplicate and empty towels are dropped, they add no
+    /// new arrangements.
+    pub fn new(towels: &[&'a str]) -> Self {
+        let mut trie = Self {
+            towels: vec![],
+            nodes: vec![Node::default()],
+        };
+
+        for towel in towels.iter().filter(|towel| !towel.is_empty()) {
+            let mut node = 0;
+            for &colour in towel.as_bytes() {
+                node = match trie.child(node, colour) {
+                    Some(child) => child,
+                    None => {
+                        trie.nodes.push(Node::default());
+                        let child = trie.nodes.len() - 1;
+                        trie.nodes[node].children.push((colour, child));
+                        child
+                    }
+                };
+            }
+
+            if trie.nodes[node].towel.is_none() {
+                trie.nodes[node].towel = Some(trie.towels.len());
+                trie.towels.push(towel);
+            }
+        }
+
+        trie
+    }
+
+    fn child(&self, node: usize, colour: u8) -> Option<usize> {
+        self.nodes[node]
+            .children
+            .iter()
+            .find(|(c, _)| *c == colour)
+            .map(|(_, child)| *child)
+    }
+
+    pub fn towel(&self, index: usize) -> &'a str {
+        self.towels[index]
+    }
+
+    /// Every towel that `pattern` starts with, shortest first, as
+    /// `(length, towel index)`.
+    pub fn prefixes<'p>(&'p self, pattern: &'p [u8]) -> impl Iterator<Item = (usize, usize)> + 'p {
+        pattern
+            .iter()
+            .scan(0, |node, &colour| {
+                *node = self.child(*node, colour)?;
+                Some(*node)
+            })
+            .enumerate()
+            .filter_map(|(i, node)| self.nodes[node].towel.map(|towel| (i + 1, towel)))
+    }
+
+    /// Number of ways to lay out `pattern`, counted in the type of `one`,
+    /// or `None` if that overflows. One pass from the back: the ways to
+    /// finish from position `i` sum the ways to finish after every towel
+    /// that matches at `i`.
+    pub fn count_arrangements<C: Count>(&self, pattern: &str, one: C) -> Option<C> {
+        let pattern = pattern.as_bytes();
+        let mut ways = vec![one.zero(); pattern.len() + 1];
+        ways[pattern.len()] = one;
+
+        for i in (0..pattern.len()).rev() {
+            for (len, _) in self.prefixes(&pattern[i..]) {
+                ways[i] = ways[i].checked_add(&ways[i + len])?;
+            }
+        }
+
+        Some(ways.swap_remove(0))
+    }
+}
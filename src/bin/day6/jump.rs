@@ -0,0 +1,178 @@
+use bitvec::{bitvec, vec::BitVec};
+
+use aoc_2024::utils::{Coord, Direction, Grid};
+
+use crate::Entity;
+
+/// For every cell and direction, the cell where the guard stops in front of
+/// the next obstacle, or `None` if it walks off the map. Walking with the
+/// table only visits turn points instead of every cell on the path.
+pub struct JumpTable {
+    rows: usize,
+    cols: usize,
+    stops: Vec<Option<Coord>>,
+}
+
+impl JumpTable {
+    pub fn new(grid: &Grid<Entity>) -> Self {
+        let (rows, cols) = grid.size();
+        let mut table = Self {
+            rows,
+            cols,
+            stops: vec![None; rows * cols * 4],
+        };
+        let is_object = |row, col| grid.get((row, col)) == &Entity::Object;
+
+        for col in 0..cols {
+            let mut stop = None;
+            for row in 0..rows {
+                if is_object(row, col) {
+                    stop = Some((row + 1, col));
+                } else {
+                    table.set((row, col), Direction::Up, stop);
+                }
+            }
+
+            let mut stop = None;
+            for row in (0..rows).rev() {
+                if is_object(row, col) {
+                    stop = row.checked_sub(1).map(|r| (r, col));
+                } else {
+                    table.set((row, col), Direction::Down, stop);
+                }
+            }
+        }
+
+        for row in 0..rows {
+            let mut stop = None;
+            for col in 0..cols {
+                if is_object(row, col) {
+                    stop = Some((row, col + 1));
+                } else {
+                    table.set((row, col), Direction::Left, stop);
+                }
+            }
+
+            let mut stop = None;
+            for col in (0..cols).rev() {
+                if is_object(row, col) {
+                    stop = col.checked_sub(1).map(|c| (row, c));
+                } else {
+                    table.set((row, col), Direction::Right, stop);
+                }
+            }
+        }
+
+        table
+    }
+
+    fn set(&mut self, pos: Coord, dir: Direction, stop: Option<Coord>) {
+        let idx = self.idx(pos, dir);
+        self.stops[idx] = stop;
+    }
+
+    fn idx(&self, pos: Coord, dir: Direction) -> usize {
+        (pos.0 * self.cols + pos.1) * 4 + dir as usize
+    }
+
+    /// Where the guard stops walking from `pos` in `dir`, taking one extra
+    /// obstacle at `extra` into account.
+    pub fn next_stop(&self, pos: Coord, dir: Direction, extra: Option<Coord>) -> Option<Coord> {
+        let stop = self.stops[self.idx(pos, dir)];
+
+        let Some(extra) = extra else {
+            return stop;
+        };
+
+        // Cell in front of the extra obstacle, if it lies on the way to `stop`
+        let blocked = match dir {
+            Direction::Up => (extra.1 == pos.1 && extra.0 < pos.0)
+                .then(|| (extra.0 + 1, pos.1))
+                .filter(|b| stop.is_none_or(|s| b.0 > s.0)),
+            Direction::Down => (extra.1 == pos.1 && extra.0 > pos.0)
+                .then(|| (extra.0 - 1, pos.1))
+                .filter(|b| stop.is_none_or(|s| b.0 < s.0)),
+            Direction::Left => (extra.0 == pos.0 && extra.1 < pos.1)
+                .then(|| (pos.0, extra.1 + 1))
+                .filter(|b| stop.is_none_or(|s| b.1 > s.1)),
+            Direction::Right => (extra.0 == pos.0 && extra.1 > pos.1)
+                .then(|| (pos.0, extra.1 - 1))
+                .filter(|b| stop.is_none_or(|s| b.1 < s.1)),
+        };
+
+        blocked.or(stop)
+    }
+
+    /// Whether the guard starting at `pos` facing `dir` ends up walking in a
+    /// loop with an extra obstacle at `extra`. Only turn points are visited,
+    /// and they are tracked in a dense bitset keyed by `(cell, direction)`.
+    pub fn loops(&self, mut pos: Coord, mut dir: Direction, extra: Option<Coord>) -> bool {
+        let mut seen: BitVec = bitvec![0; self.rows * self.cols * 4];
+
+        while let Some(stop) = self.next_stop(pos, dir, extra) {
+            pos = stop;
+            dir = dir.turn_right();
+
+            let idx = self.idx(pos, dir);
+            if seen[idx] {
+                return true;
+            }
+            seen.set(idx, true);
+        }
+
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use aoc_2024::utils::DIRECTIONS;
+
+    use super::*;
+    use crate::{loop_obstacles, parse_input, test::example, visited_spaces};
+
+    #[test]
+    fn example_counts() {
+        let world = example();
+
+        let path = world.walk().unwrap();
+        assert_eq!(visited_spaces(&path), 41);
+        assert_eq!(loop_obstacles(&world, &path), 6);
+    }
+
+    #[test]
+    fn extra_obstacle() {
+        let world = parse_input(
+            [
+                "...#...", ".......", ".......", "#..^...", ".......", ".......", "...#...",
+            ]
+            .map(String::from)
+            .to_vec(),
+        );
+        let table = JumpTable::new(&world.grid);
+        let (guard, size) = ((3, 3), world.grid.size());
+
+        for dir in DIRECTIONS {
+            let stop = match dir {
+                Direction::Up => Some((1, 3)),
+                Direction::Down => Some((5, 3)),
+                Direction::Left => Some((3, 1)),
+                Direction::Right => None,
+            };
+            assert_eq!(table.next_stop(guard, dir, None), stop, "{:?}", dir);
+
+            // Directly in front the guard can't move at all
+            let front = dir.step(guard, size).unwrap();
+            assert_eq!(table.next_stop(guard, dir, Some(front)), Some(guard));
+
+            let ahead = dir.step(front, size).unwrap();
+            assert_eq!(table.next_stop(guard, dir, Some(ahead)), Some(front));
+
+            // Behind the guard or beside its path it changes nothing
+            let behind = dir.opposite().step(guard, size).unwrap();
+            assert_eq!(table.next_stop(guard, dir, Some(behind)), stop);
+            let beside = dir.turn_right().step(front, size).unwrap();
+            assert_eq!(table.next_stop(guard, dir, Some(beside)), stop);
+        }
+    }
+}
@@ -1,100 +1,19 @@
+mod jump;
+mod replay;
+
 use std::{
     collections::{HashMap, HashSet},
     fmt,
+    io::stdout,
+    time::Duration,
 };
 
-use aoc_2024::input::read_lines;
+use aoc_2024::{
+    input::read_lines,
+    utils::{Coord, Direction, Grid},
+};
 use itertools::Itertools;
-
-struct Grid<T: Copy + Default> {
-    data: Vec<T>,
-    rows: usize,
-    cols: usize,
-}
-
-impl<T: Copy + Default> Grid<T> {
-    pub fn zeros(rows: usize, cols: usize) -> Self {
-        Grid {
-            data: vec![T::default(); rows * cols],
-            rows,
-            cols,
-        }
-    }
-
-    pub fn set(&mut self, row: usize, col: usize, value: T) {
-        let idx = self.idx(row, col);
-        self.data[idx] = value;
-    }
-
-    pub fn get(&self, row: usize, col: usize) -> &T {
-        &self.data[self.idx(row, col)]
-    }
-
-    pub fn row(&self, row: usize) -> &[T] {
-        &self.data[row * self.cols..(row + 1) * self.cols]
-    }
-
-    fn idx(&self, row: usize, col: usize) -> usize {
-        row * self.cols + col
-    }
-}
-
-#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
-enum Direction {
-    #[default]
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-impl Direction {
-    pub fn rotate_ccw(&self) -> Self {
-        match *self {
-            Direction::Up => Direction::Right,
-            Direction::Down => Direction::Left,
-            Direction::Left => Direction::Up,
-            Direction::Right => Direction::Down,
-        }
-    }
-
-    pub fn move_point(
-        &self,
-        point: (usize, usize),
-        bounds: (usize, usize),
-    ) -> Option<(usize, usize)> {
-        match self {
-            Direction::Up => {
-                if point.0 == 0 {
-                    None
-                } else {
-                    Some((point.0 - 1, point.1))
-                }
-            }
-            Direction::Down => {
-                if point.0 == bounds.0 - 1 {
-                    None
-                } else {
-                    Some((point.0 + 1, point.1))
-                }
-            }
-            Direction::Left => {
-                if point.1 == 0 {
-                    None
-                } else {
-                    Some((point.0, point.1 - 1))
-                }
-            }
-            Direction::Right => {
-                if point.1 == bounds.1 - 1 {
-                    None
-                } else {
-                    Some((point.0, point.1 + 1))
-                }
-            }
-        }
-    }
-}
+use jump::JumpTable;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum Entity {
@@ -140,9 +59,21 @@ impl Entity {
     }
 }
 
+/// Outcome of a full walk: the path until the guard leaves the map, or the
+/// path until it would repeat a state, with the repeating part starting at
+/// `cycle_start`.
+enum Walk {
+    Exit(Vec<(Coord, Direction)>),
+    Loop {
+        path: Vec<(Coord, Direction)>,
+        cycle_start: usize,
+    },
+}
+
+#[derive(Clone)]
 struct World {
     pub grid: Grid<Entity>,
-    pub guard_position: Option<(usize, usize)>,
+    pub guard_position: Option<Coord>,
     pub guard_direction: Direction,
 }
 
@@ -150,15 +81,12 @@ impl World {
     pub fn new(grid: Grid<Entity>) -> Self {
         let mut guard_position = None;
         let mut guard_direction = Direction::Up;
-        (0..grid.rows)
-            .cartesian_product(0..grid.cols)
-            .for_each(|(row, col)| match grid.get(row, col) {
-                Entity::Guard(dir) => {
-                    guard_position = Some((row, col));
-                    guard_direction = *dir;
-                }
-                _ => (),
-            });
+        grid.indexed_iter().for_each(|(pos, entity)| {
+            if let Entity::Guard(dir) = entity {
+                guard_position = Some(pos);
+                guard_direction = *dir;
+            }
+        });
 
         assert!(guard_position.is_some());
         Self {
@@ -168,49 +96,107 @@ impl World {
         }
     }
 
-    pub fn walk(&self) -> Option<Vec<((usize, usize), Direction)>> {
+    pub fn walk(&self) -> Option<Vec<(Coord, Direction)>> {
+        match self.trace() {
+            Walk::Exit(path) => Some(path),
+            Walk::Loop { .. } => None,
+        }
+    }
+
+    pub fn trace(&self) -> Walk {
         let mut path = vec![];
-        path.reserve(self.grid.rows * self.grid.cols);
+        path.reserve(self.grid.size().0 * self.grid.size().1);
         let mut path_len = path.len();
 
-        let mut visited_spaces = HashSet::with_capacity(self.grid.rows * self.grid.cols);
+        // Index in `path` where each (position, direction) was first seen
+        let mut visited_spaces = HashMap::with_capacity(self.grid.size().0 * self.grid.size().1);
 
         if let Some(guard_position) = self.guard_position {
             let mut pos = guard_position;
             let mut dir = self.guard_direction;
-            while let Some((next_pos, new_dir)) = self.find_next_turnpoint(&mut path, pos, dir) {
-                for (p, d) in &path[path_len..] {
-                    if !visited_spaces.insert((*p, *d)) {
-                        return None;
+            loop {
+                let next = self.find_next_turnpoint(&mut path, pos, dir);
+                for i in path_len..path.len() {
+                    if let Some(&cycle_start) = visited_spaces.get(&path[i]) {
+                        path.truncate(i);
+                        return Walk::Loop { path, cycle_start };
                     }
+                    visited_spaces.insert(path[i], i);
                 }
                 path_len = path.len();
 
-                pos = next_pos;
-                dir = new_dir;
+                match next {
+                    Some((next_pos, new_dir)) => {
+                        pos = next_pos;
+                        dir = new_dir;
+                    }
+                    None => break,
+                }
             }
+        }
 
-            for (p, d) in &path[path_len..] {
-                visited_spaces.insert((*p, *d));
+        Walk::Exit(path)
+    }
+
+    /// Copy of the world with an extra obstacle at `pos`.
+    pub fn with_obstacle(&self, pos: Coord) -> Result<World, String> {
+        let (rows, cols) = self.grid.size();
+        if pos.0 >= rows || pos.1 >= cols {
+            return Err(format!("{:?} is outside the {}x{} map", pos, rows, cols));
+        }
+        if !matches!(self.grid.get(pos), Entity::Empty { .. }) {
+            return Err(format!("{:?} is not an empty space", pos));
+        }
+
+        let mut world = self.clone();
+        world.grid.set(pos, Entity::Object);
+        Ok(world)
+    }
+
+    /// Marks every empty space on `path` as visited.
+    pub fn mark_visited(&mut self, path: &[(Coord, Direction)]) {
+        for (pos, _) in path {
+            if let Entity::Empty { visited } = self.grid.get_mut(*pos) {
+                *visited = true;
             }
         }
+    }
 
-        Some(path)
+    /// Renders the map like `Display`, wrapping every space for which
+    /// `colour` returns an ANSI colour code in that colour.
+    pub fn render(&self, colour: impl Fn(Coord) -> Option<&'static str>) -> String {
+        self.grid
+            .rows()
+            .enumerate()
+            .map(|(row, entities)| {
+                entities
+                    .iter()
+                    .enumerate()
+                    .map(|(col, entity)| {
+                        let c = Into::<char>::into(entity.to_ascii());
+                        match colour((row, col)) {
+                            Some(code) => format!("\x1b[{}m{}\x1b[0m", code, c),
+                            None => c.to_string(),
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .join("\n")
     }
 
     fn find_next_turnpoint(
         &self,
-        path: &mut Vec<((usize, usize), Direction)>,
-        pos: (usize, usize),
+        path: &mut Vec<(Coord, Direction)>,
+        pos: Coord,
         dir: Direction,
-    ) -> Option<((usize, usize), Direction)> {
+    ) -> Option<(Coord, Direction)> {
         let mut pos = pos;
         loop {
             path.push((pos, dir));
-            let new_pos = dir.move_point(pos, (self.grid.rows, self.grid.cols));
+            let new_pos = dir.step(pos, self.grid.size());
             if let Some(new_pos) = new_pos {
-                if self.grid.get(new_pos.0, new_pos.1) == &Entity::Object {
-                    return Some((pos, dir.rotate_ccw()));
+                if self.grid.get(new_pos) == &Entity::Object {
+                    return Some((pos, dir.turn_right()));
                 } else {
                     pos = new_pos;
                 }
@@ -224,16 +210,7 @@ impl World {
 // Implement `Display` for `MinMax`.
 impl fmt::Display for World {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let out = (0..self.grid.rows)
-            .map(|row| {
-                self.grid
-                    .row(row)
-                    .iter()
-                    .map(|entity| Into::<char>::into(entity.to_ascii()))
-                    .collect::<String>()
-            })
-            .join("\n");
-        write!(f, "{}", out)
+        write!(f, "{}", self.render(|_| None))
     }
 }
 
@@ -246,65 +223,144 @@ fn parse_input(input: Vec<String>) -> World {
         row.as_bytes()
             .iter()
             .enumerate()
-            .for_each(|(j, c)| grid.set(i, j, Entity::from_ascii(*c)))
+            .for_each(|(j, c)| grid.set((i, j), Entity::from_ascii(*c)))
     });
 
     World::new(grid)
 }
 
-fn main() {
+fn parse_coord(arg: &str) -> Result<Coord, Box<dyn std::error::Error>> {
+    let (row, col) = arg
+        .split_once(',')
+        .ok_or_else(|| format!("expected ROW,COL, got {:?}", arg))?;
+    Ok((row.trim().parse()?, col.trim().parse()?))
+}
+
+fn visited_spaces(path: &[(Coord, Direction)]) -> usize {
+    path.iter()
+        .map(|(pos, _)| *pos)
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Number of spaces on the guard's `path` where one extra obstacle makes it
+/// walk in a loop.
+fn loop_obstacles(world: &World, path: &[(Coord, Direction)]) -> usize {
+    let jump_table = JumpTable::new(&world.grid);
+
+    let mut new_object_results = HashMap::with_capacity(path.len());
+    for i in 0..path.len() - 1 {
+        if path[i]
+            .1
+            .step(path[i].0, world.grid.size())
+            .is_some_and(|p| p == path[i + 1].0)
+        {
+            let (pos, _) = path[i + 1];
+
+            if new_object_results.get(&pos).is_some_and(|res| *res) {
+                continue;
+            }
+
+            new_object_results.insert(pos, jump_table.loops(path[i].0, path[i].1, Some(pos)));
+        }
+    }
+
+    new_object_results.values().filter(|v| **v).count()
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     if let Ok(lines) = read_lines("./inputs/day6/input.txt") {
         // Parsing
         let input = lines.flatten().collect::<Vec<_>>();
         assert!(!input.is_empty());
 
-        let mut world = parse_input(input);
-
-        let path = if let Some(path) = world.walk() {
-            let visited = path
-                .iter()
-                .map(|(pos, _)| *pos)
-                .collect::<HashSet<_>>()
-                .len();
-            println!("Number of visited spaces: {}", visited);
+        let world = parse_input(input);
+
+        // --replay [ROW,COL [DELAY_MS]] or --dump ROW,COL
+        let args = std::env::args().skip(1).collect_vec();
+        match args.first().map(|arg| arg.as_str()) {
+            Some("--replay") => {
+                let obstacle = args.get(1).map(|arg| parse_coord(arg)).transpose()?;
+                let delay = args.get(2).map(|ms| ms.parse()).transpose()?.unwrap_or(50);
+                let world = match obstacle {
+                    Some(pos) => world.with_obstacle(pos)?,
+                    None => world,
+                };
+                replay::replay(&world, obstacle, Duration::from_millis(delay));
+                return Ok(());
+            }
+            Some("--dump") => {
+                let obstacle = parse_coord(args.get(1).ok_or("--dump needs ROW,COL")?)?;
+                replay::dump_loop(&mut stdout(), &world.with_obstacle(obstacle)?, obstacle)?;
+                return Ok(());
+            }
+            _ => (),
+        }
 
-            path
-        } else {
-            return;
+        let Some(path) = world.walk() else {
+            return Ok(());
         };
+        println!("Number of visited spaces: {}", visited_spaces(&path));
+        println!(
+            "There are {} ways to create a guard loop.",
+            loop_obstacles(&world, &path)
+        );
+    }
 
-        let mut new_object_results = HashMap::with_capacity(path.len());
-        for i in 0..path.len() - 1 {
-            if path[i]
-                .1
-                .move_point(path[i].0, (world.grid.rows, world.grid.cols))
-                .is_some_and(|p| p == path[i + 1].0)
-            {
-                let (pos, _) = path[i + 1];
-
-                if new_object_results.get(&pos).is_some_and(|res| *res) {
-                    continue;
-                }
+    Ok(())
+}
 
-                world.guard_position = Some(path[i].0);
-                world.guard_direction = path[i].1;
-                world.grid.set(pos.0, pos.1, Entity::Object);
+#[cfg(test)]
+mod test {
+    use super::*;
 
-                if world.walk().is_none() {
-                    new_object_results.insert(pos, true);
-                } else {
-                    new_object_results.insert(pos, false);
-                }
+    pub fn example() -> World {
+        let lines = read_lines("./inputs/day6/test.txt").unwrap();
+        parse_input(lines.map_while(Result::ok).collect())
+    }
 
-                world
-                    .grid
-                    .set(pos.0, pos.1, Entity::Empty { visited: false });
-            }
+    /// The state after the last one on `path`.
+    fn next_state(world: &World, path: &[(Coord, Direction)]) -> (Coord, Direction) {
+        let &(pos, dir) = path.last().unwrap();
+        match dir.step(pos, world.grid.size()) {
+            Some(next) if world.grid.get(next) == &Entity::Object => (pos, dir.turn_right()),
+            Some(next) => (next, dir),
+            None => panic!("guard leaves the map"),
         }
+    }
 
-        println!(
-            "There are {} ways to create a guard loop.",
-            new_object_results.values().filter(|v| **v).count()
-        );
+    #[test]
+    fn trace_cycles() {
+        let world = example();
+        assert!(matches!(world.trace(), Walk::Exit(path) if visited_spaces(&path) == 41));
+
+        // Right next to the guard: up, right, down and left back to the start
+        let Walk::Loop { path, cycle_start } = world.with_obstacle((6, 3)).unwrap().trace() else {
+            panic!("expected a loop");
+        };
+        assert_eq!(cycle_start, 0);
+        assert_eq!(path.len() - cycle_start, 22);
+
+        // Here the guard only enters the loop after a while
+        let world = world.with_obstacle((7, 6)).unwrap();
+        let Walk::Loop { path, cycle_start } = world.trace() else {
+            panic!("expected a loop");
+        };
+        assert!(cycle_start > 0);
+        assert_eq!(next_state(&world, &path), path[cycle_start]);
+        assert!(!path[cycle_start..].contains(&path[0]));
+    }
+
+    #[test]
+    fn obstacle_placement() {
+        let world = example();
+        assert!(world.with_obstacle((10, 0)).is_err());
+        assert!(world.with_obstacle((0, 10)).is_err());
+        // An existing obstacle and the guard
+        assert!(world.with_obstacle((0, 4)).is_err());
+        assert!(world.with_obstacle((6, 4)).is_err());
+
+        let with = world.with_obstacle((0, 0)).unwrap();
+        assert!(with.grid.get((0, 0)) == &Entity::Object);
     }
 }
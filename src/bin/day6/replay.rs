@@ -0,0 +1,139 @@
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+    thread,
+    time::Duration,
+};
+
+use aoc_2024::utils::{pause, Coord, Direction};
+
+use crate::{Entity, Walk, World};
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+const RED: &str = "31";
+const YELLOW: &str = "33";
+
+/// Replays the guard's walk frame by frame, one step per frame. Spaces on
+/// the loop cycle turn red once the guard enters it and the extra obstacle
+/// is drawn in yellow. A zero `delay` waits for Enter between frames.
+pub fn replay(world: &World, obstacle: Option<Coord>, delay: Duration) {
+    let (path, cycle_start) = match world.trace() {
+        Walk::Exit(path) => (path, None),
+        Walk::Loop { path, cycle_start } => (path, Some(cycle_start)),
+    };
+    let cycle = cycle_cells(&path, cycle_start);
+
+    let mut frame = world.clone();
+    for (step, &(pos, dir)) in path.iter().enumerate() {
+        if step > 0 {
+            frame
+                .grid
+                .set(path[step - 1].0, Entity::Empty { visited: true });
+        }
+        frame.grid.set(pos, Entity::Guard(dir));
+
+        let in_cycle = cycle_start.is_some_and(|start| step >= start);
+        let rendered = frame.render(|c| {
+            if Some(c) == obstacle {
+                Some(YELLOW)
+            } else if in_cycle && cycle.contains(&c) {
+                Some(RED)
+            } else {
+                None
+            }
+        });
+        println!("{}{}", CLEAR_SCREEN, rendered);
+        println!("Step {}/{}", step + 1, path.len());
+
+        if delay.is_zero() {
+            pause();
+        } else {
+            thread::sleep(delay);
+        }
+    }
+
+    match cycle_start {
+        Some(start) => println!("Guard loops, cycle of {} steps", path.len() - start),
+        None => println!("Guard leaves the map"),
+    }
+}
+
+/// Writes out the looping part of the walk, or reports that the guard
+/// leaves.
+pub fn dump_loop(out: &mut impl Write, world: &World, obstacle: Coord) -> io::Result<()> {
+    match world.trace() {
+        Walk::Exit(path) => {
+            writeln!(
+                out,
+                "No loop with an obstacle at {:?}, guard leaves after {} steps",
+                obstacle,
+                path.len()
+            )?;
+        }
+        Walk::Loop { path, cycle_start } => {
+            writeln!(
+                out,
+                "Obstacle at {:?} loops after {} steps, cycle of {} steps:",
+                obstacle,
+                cycle_start,
+                path.len() - cycle_start
+            )?;
+            for (pos, dir) in path[cycle_start..].iter() {
+                writeln!(out, "{:?} {:?}", pos, dir)?;
+            }
+
+            let mut marked = world.clone();
+            marked.mark_visited(&path);
+            let cycle = cycle_cells(&path, Some(cycle_start));
+            writeln!(
+                out,
+                "{}",
+                marked.render(|c| {
+                    if c == obstacle {
+                        Some(YELLOW)
+                    } else {
+                        cycle.contains(&c).then_some(RED)
+                    }
+                })
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn cycle_cells(path: &[(Coord, Direction)], cycle_start: Option<usize>) -> HashSet<Coord> {
+    cycle_start
+        .map(|start| path[start..].iter().map(|(pos, _)| *pos).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::example;
+
+    #[test]
+    fn dump() {
+        let world = example();
+
+        let mut out = vec![];
+        dump_loop(&mut out, &world.with_obstacle((6, 3)).unwrap(), (6, 3)).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let mut lines = out.lines();
+        assert_eq!(
+            lines.next(),
+            Some("Obstacle at (6, 3) loops after 0 steps, cycle of 22 steps:")
+        );
+        assert_eq!(lines.next(), Some("(6, 4) Up"));
+        // Every cycle step, then the map with the obstacle in yellow
+        assert_eq!(lines.clone().count(), 21 + 10);
+        assert!(out.contains(&format!("\x1b[{}m#\x1b[0m", YELLOW)));
+
+        let mut out = vec![];
+        dump_loop(&mut out, &world.with_obstacle((0, 0)).unwrap(), (0, 0)).unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .starts_with("No loop with an obstacle at (0, 0)"));
+    }
+}
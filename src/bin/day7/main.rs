@@ -1,17 +1,14 @@
-use std::collections::VecDeque;
+mod operator;
+mod reverse;
+
+use std::time::Instant;
 
 use aoc_2024::input::read_lines;
 use itertools::Itertools;
+use operator::Operator;
+use reverse::find_operators_reverse;
 
-#[allow(dead_code)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Operator {
-    Add,
-    Mult,
-    Concat,
-}
-
-static I64_LOG_10_TABLE: [i64; 17] = [
+static I64_LOG_10_TABLE: [i64; 18] = [
     9,
     99,
     999,
@@ -29,119 +26,130 @@ static I64_LOG_10_TABLE: [i64; 17] = [
     999999999999999,
     9999999999999999,
     99999999999999999,
+    999999999999999999,
 ];
 
+/// Writes the digits of `right` after those of `left`, or `None` for
+/// negative operands or if the result doesn't fit in an `i64`.
 #[inline]
-fn concat_i64(left: i64, right: i64) -> i64 {
+fn concat_i64(left: i64, right: i64) -> Option<i64> {
     //left * 10i64.pow(right.ilog10() + 1) + right
-    for i in 0..17 {
-        if right <= I64_LOG_10_TABLE[i] {
-            return left * (I64_LOG_10_TABLE[i] + 1) + right;
-        }
+    if left < 0 || right < 0 {
+        return None;
     }
+    let max = I64_LOG_10_TABLE.iter().find(|&&max| right <= max)?;
 
-    unreachable!()
+    left.checked_mul(max + 1)?.checked_add(right)
 }
 
-#[allow(dead_code)]
-fn find_operators_with_op_sequences(result: i64, terms: &[i64]) -> Vec<Vec<Operator>> {
-    let mut sequences: Vec<Vec<Operator>> = vec![];
-    let num_terms = terms.len();
-
-    let mut options: VecDeque<(i64, Vec<Operator>)> = VecDeque::new();
-    options.push_back((terms[0], vec![]));
-    while let Some((res, mut ops)) = options.pop_front() {
-        let next_term = terms[ops.len() + 1];
-
-        if res * next_term <= result {
-            let mut ops = ops.clone();
-            ops.push(Operator::Mult);
-            if ops.len() == num_terms - 1 {
-                if res * next_term == result {
-                    sequences.push(ops);
-                }
-            } else {
-                options.push_front((res * next_term, ops));
-            }
-        }
+/// Finds operators that make `terms` evaluate to `result` left to right,
+/// depth first. Returns the first working sequence as a witness.
+fn find_operators<'o>(
+    result: i64,
+    terms: &[i64],
+    ops: &[&'o dyn Operator],
+) -> Option<Vec<&'o dyn Operator>> {
+    let prune = operator::monotone(terms, ops);
+    let mut chosen = Vec::with_capacity(terms.len());
 
-        if res + next_term <= result {
-            let mut ops = ops.clone();
-            ops.push(Operator::Add);
-            if ops.len() == num_terms - 1 {
-                if res + next_term == result {
-                    sequences.push(ops);
-                }
-            } else {
-                options.push_back((res + next_term, ops));
+    search(terms[0], &terms[1..], result, ops, prune, &mut chosen).then_some(chosen)
+}
+
+fn search<'o>(
+    value: i64,
+    rest: &[i64],
+    result: i64,
+    ops: &[&'o dyn Operator],
+    prune: bool,
+    chosen: &mut Vec<&'o dyn Operator>,
+) -> bool {
+    match rest {
+        [] => value == result,
+        // The last operator has to turn `value` into `result` exactly
+        [last] => match ops.iter().find(|op| op.invert(result, *last).admits(value)) {
+            Some(op) => {
+                chosen.push(*op);
+                true
             }
-        }
+            None => false,
+        },
+        [next, rest @ ..] => {
+            for op in ops {
+                let Some(next_value) = op.apply(value, *next) else {
+                    continue;
+                };
+                if prune && next_value > result {
+                    continue;
+                }
 
-        {
-            let cat = concat_i64(res, next_term);
-            if cat <= result {
-                ops.push(Operator::Concat);
-                if ops.len() == num_terms - 1 {
-                    if cat == result {
-                        sequences.push(ops);
-                    }
-                } else {
-                    options.push_front((cat, ops));
+                chosen.push(*op);
+                if search(next_value, rest, result, ops, prune, chosen) {
+                    return true;
                 }
+                chosen.pop();
             }
+
+            false
         }
     }
-
-    sequences
 }
 
-fn find_operators(result: i64, terms: &[i64]) -> bool {
-    let num_terms = terms.len();
+/// Parses a comma separated list of operator symbols, e.g. `+,*,||`.
+fn parse_ops(symbols: &str) -> Result<Vec<&'static dyn Operator>, String> {
+    symbols
+        .split(',')
+        .map(|symbol| {
+            operator::from_symbol(symbol).ok_or_else(|| format!("unknown operator {:?}", symbol))
+        })
+        .collect()
+}
 
-    let mut options = VecDeque::<(i64, usize)>::with_capacity(128);
+/// Sum of the results that some choice of `ops` can produce, printing
+/// each solution if `witness` is set.
+fn calibration_total(input: &[(i64, Vec<i64>)], ops: &[&dyn Operator], witness: bool) -> i64 {
+    let mut total = 0;
+    for (result, terms) in input.iter() {
+        if let Some(chosen) = find_operators_reverse(*result, terms, ops) {
+            if witness {
+                println!("{}", operator::format_expression(*result, terms, &chosen));
+            }
+            total += result;
+        }
+    }
 
-    options.push_front((terms[0], 0));
-    while let Some((res, num_ops)) = options.pop_back() {
-        let next_term = terms[num_ops + 1];
+    total
+}
 
-        if res * next_term <= result {
-            if num_ops + 1 == num_terms - 1 {
-                if res * next_term == result {
-                    return true;
-                }
-            } else {
-                options.push_back((res * next_term, num_ops + 1));
-            }
+type Solver = for<'o> fn(i64, &[i64], &[&'o dyn Operator]) -> Option<Vec<&'o dyn Operator>>;
 
-            {
-                let cat = concat_i64(res, next_term);
-                if cat <= result {
-                    if num_ops + 1 == num_terms - 1 {
-                        if cat == result {
-                            return true;
-                        }
-                    } else {
-                        options.push_front((cat, num_ops + 1));
-                    }
-                }
-            }
-        }
+/// Times both solvers over the whole input, `rounds` times each.
+fn benchmark(input: &[(i64, Vec<i64>)], ops: &[&dyn Operator], rounds: usize) {
+    let solvers: [(&str, Solver); 2] = [
+        ("Forward", find_operators),
+        ("Reverse", find_operators_reverse),
+    ];
 
-        if res + next_term <= result {
-            if num_ops + 1 == num_terms - 1 {
-                if res + next_term == result {
-                    return true;
-                }
-            } else {
-                options.push_back((res + next_term, num_ops + 1));
-            }
+    for (name, solver) in solvers {
+        let start = Instant::now();
+        let mut total = 0;
+        for _ in 0..rounds {
+            total = input
+                .iter()
+                .filter(|(result, terms)| solver(*result, terms, ops).is_some())
+                .map(|(result, _)| result)
+                .sum::<i64>();
         }
+        let elapsed = start.elapsed();
+        println!(
+            "{:<8} {:>10.2?} per round (total {})",
+            name,
+            elapsed / rounds.max(1) as u32,
+            total
+        );
     }
-
-    false
 }
 
-fn main() {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     if let Ok(lines) = read_lines("./inputs/day7/input.txt") {
         let input = lines
             .flatten()
@@ -163,11 +171,169 @@ fn main() {
             })
             .collect_vec();
 
-        let total = input
+        // --ops SYMBOLS to pick the operators, --witness to print solutions
+        let args = std::env::args().collect_vec();
+        let ops = match args.iter().position(|arg| arg == "--ops") {
+            Some(i) => Some(parse_ops(
+                args.get(i + 1).ok_or("--ops needs a list of operators")?,
+            )?),
+            None => None,
+        };
+        let witness = args.iter().any(|arg| arg == "--witness");
+
+        // --bench [ROUNDS] to compare the forward and reverse solvers
+        let mut bench = args.iter().skip_while(|arg| *arg != "--bench");
+        if bench.next().is_some() {
+            let rounds = bench.next().map(|n| n.parse()).transpose()?.unwrap_or(10);
+            let ops = ops.unwrap_or_else(|| operator::PART_TWO.to_vec());
+            benchmark(&input, &ops, rounds);
+            return Ok(());
+        }
+
+        match ops {
+            Some(ops) => println!("Total: {}", calibration_total(&input, &ops, witness)),
+            None => {
+                let star1 = calibration_total(&input, &operator::PART_ONE, witness);
+                println!("Star 1: {}", star1);
+                let star2 = calibration_total(&input, &operator::PART_TWO, witness);
+                println!("Star 2: {}", star2);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const EXAMPLE: [(i64, &[i64]); 9] = [
+        (190, &[10, 19]),
+        (3267, &[81, 40, 27]),
+        (83, &[17, 5]),
+        (156, &[15, 6]),
+        (7290, &[6, 8, 6, 15]),
+        (161011, &[16, 10, 13]),
+        (192, &[17, 8, 14]),
+        (21037, &[9, 7, 18, 13]),
+        (292, &[11, 6, 16, 20]),
+    ];
+
+    fn total(ops: &[&dyn Operator]) -> i64 {
+        EXAMPLE
             .iter()
-            .filter_map(|(result, terms)| find_operators(*result, terms).then_some(*result))
-            .sum::<i64>();
+            .filter(|(result, terms)| find_operators(*result, terms, ops).is_some())
+            .map(|(result, _)| result)
+            .sum()
+    }
+
+    #[test]
+    fn example_totals() {
+        assert_eq!(total(&parse_ops("+,*").unwrap()), 3749);
+        assert_eq!(total(&operator::PART_TWO), 11387);
+    }
+
+    #[test]
+    fn witness_expressions() {
+        let chosen = find_operators(3267, &[81, 40, 27], &operator::PART_TWO).unwrap();
+        assert_eq!(
+            operator::format_expression(3267, &[81, 40, 27], &chosen),
+            "81 + 40 * 27 = 3267"
+        );
+
+        let chosen = find_operators(7290, &[6, 8, 6, 15], &operator::PART_TWO).unwrap();
+        assert_eq!(
+            operator::format_expression(7290, &[6, 8, 6, 15], &chosen),
+            "6 * 8 || 6 * 15 = 7290"
+        );
+    }
+
+    #[test]
+    fn other_operators() {
+        let ops = parse_ops("-,^").unwrap();
+        let chosen = find_operators(3, &[10, 4, 5], &ops).unwrap();
+        assert_eq!(
+            operator::format_expression(3, &[10, 4, 5], &chosen),
+            "10 - 4 ^ 5 = 3"
+        );
+        assert!(find_operators(100, &[10, 4, 5], &ops).is_none());
+        assert!(parse_ops("+,%").is_err());
+    }
+
+    #[test]
+    fn reverse_agrees_with_forward() {
+        for ops in [parse_ops("+,*").unwrap(), operator::PART_TWO.to_vec()] {
+            for (result, terms) in EXAMPLE {
+                let forward = find_operators(result, terms, &ops);
+                let reverse = find_operators_reverse(result, terms, &ops);
+                assert_eq!(forward.is_some(), reverse.is_some());
+
+                // Witnesses may differ, but both have to evaluate correctly
+                if let Some(chosen) = reverse {
+                    let value = terms[1..]
+                        .iter()
+                        .zip(chosen)
+                        .try_fold(terms[0], |acc, (term, op)| op.apply(acc, *term));
+                    assert_eq!(value, Some(result));
+                }
+            }
+        }
+
+        let ops = parse_ops("-,^").unwrap();
+        let chosen = find_operators_reverse(3, &[10, 4, 5], &ops).unwrap();
+        assert_eq!(
+            operator::format_expression(3, &[10, 4, 5], &chosen),
+            "10 - 4 ^ 5 = 3"
+        );
+    }
+
+    /// Whether both solvers find a solution, checking that they agree.
+    fn solvable(result: i64, terms: &[i64], ops: &[&dyn Operator]) -> bool {
+        let forward = find_operators(result, terms, ops).is_some();
+        assert_eq!(
+            forward,
+            find_operators_reverse(result, terms, ops).is_some()
+        );
+        forward
+    }
+
+    #[test]
+    fn zero_and_negative_terms() {
+        assert!(solvable(0, &[5, 0], &operator::PART_ONE));
+        // Partial values above the result can still drop back to it
+        assert!(solvable(15, &[20, 0, 15], &operator::PART_ONE));
+        assert!(solvable(15, &[20, 3, 0, 15], &operator::PART_TWO));
+        assert!(!solvable(16, &[20, 3, 0, 15], &operator::PART_ONE));
+        assert!(solvable(30, &[3, 0], &operator::PART_TWO));
+
+        assert!(solvable(-6, &[2, -3], &operator::PART_ONE));
+        assert!(solvable(1, &[-2, 3], &operator::PART_ONE));
+        assert!(solvable(-7, &[-2, 3, -1, 1], &operator::PART_ONE));
+        assert!(!solvable(-23, &[-2, 3], &operator::PART_TWO));
+    }
+
+    #[test]
+    fn concat_out_of_range() {
+        assert_eq!(concat_i64(123, 45), Some(12345));
+        assert_eq!(concat_i64(12, 0), Some(120));
+        assert_eq!(
+            concat_i64(1, 100_000_000_000_000_000),
+            Some(1_100_000_000_000_000_000)
+        );
+        assert_eq!(concat_i64(10, 100_000_000_000_000_000), None);
+        assert_eq!(concat_i64(1, 1_000_000_000_000_000_000), None);
+        assert_eq!(concat_i64(-1, 2), None);
 
-        dbg!(total);
+        assert!(!solvable(
+            i64::MAX,
+            &[9, 999_999_999_999_999_999],
+            &operator::PART_TWO
+        ));
+        assert!(!solvable(
+            i64::MAX,
+            &[1_000_000_000_000_000_000, 1_000_000_000_000_000_000],
+            &operator::PART_TWO
+        ));
     }
 }
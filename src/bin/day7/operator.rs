@@ -0,0 +1,176 @@
+use crate::concat_i64;
+
+/// A binary operator, evaluated strictly left to right.
+pub trait Operator {
+    fn symbol(&self) -> &'static str;
+
+    /// `left op right`, or `None` if that is undefined or overflows.
+    fn apply(&self, left: i64, right: i64) -> Option<i64>;
+
+    /// The `left` with `left op right == result`. Lets a solver check the
+    /// last operation without trying it, or work backwards from the result.
+    fn invert(&self, result: i64, right: i64) -> Inverse;
+
+    /// Whether `left op right >= left` for every non-negative `left`, so
+    /// that values above the result can be dropped early.
+    fn non_decreasing(&self, _right: i64) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Inverse {
+    /// No `left` gives the result.
+    Impossible,
+    Exactly(i64),
+    /// Every `left` gives the result, like `left * 0 == 0`.
+    Any,
+}
+
+impl Inverse {
+    fn from_option(left: Option<i64>) -> Self {
+        left.map_or(Inverse::Impossible, Inverse::Exactly)
+    }
+
+    /// Whether `left` gives the result.
+    pub fn admits(self, left: i64) -> bool {
+        match self {
+            Inverse::Impossible => false,
+            Inverse::Exactly(value) => value == left,
+            Inverse::Any => true,
+        }
+    }
+}
+
+pub struct Add;
+pub struct Mul;
+pub struct Concat;
+pub struct Sub;
+pub struct Xor;
+
+impl Operator for Add {
+    fn symbol(&self) -> &'static str {
+        "+"
+    }
+
+    fn apply(&self, left: i64, right: i64) -> Option<i64> {
+        left.checked_add(right)
+    }
+
+    fn invert(&self, result: i64, right: i64) -> Inverse {
+        Inverse::from_option(result.checked_sub(right))
+    }
+
+    fn non_decreasing(&self, right: i64) -> bool {
+        right >= 0
+    }
+}
+
+impl Operator for Mul {
+    fn symbol(&self) -> &'static str {
+        "*"
+    }
+
+    fn apply(&self, left: i64, right: i64) -> Option<i64> {
+        left.checked_mul(right)
+    }
+
+    fn invert(&self, result: i64, right: i64) -> Inverse {
+        match right {
+            0 if result == 0 => Inverse::Any,
+            _ if result.checked_rem(right) != Some(0) => Inverse::Impossible,
+            _ => Inverse::from_option(result.checked_div(right)),
+        }
+    }
+
+    fn non_decreasing(&self, right: i64) -> bool {
+        right >= 1
+    }
+}
+
+impl Operator for Concat {
+    fn symbol(&self) -> &'static str {
+        "||"
+    }
+
+    fn apply(&self, left: i64, right: i64) -> Option<i64> {
+        concat_i64(left, right)
+    }
+
+    /// Strips the digits of `right` off the end of `result`.
+    fn invert(&self, result: i64, right: i64) -> Inverse {
+        if right < 0 || result < right {
+            return Inverse::Impossible;
+        }
+        let Some(shift) = 10i64.checked_pow(right.checked_ilog10().unwrap_or(0) + 1) else {
+            return Inverse::Impossible;
+        };
+
+        match (result - right) % shift {
+            0 => Inverse::Exactly((result - right) / shift),
+            _ => Inverse::Impossible,
+        }
+    }
+
+    fn non_decreasing(&self, right: i64) -> bool {
+        right >= 0
+    }
+}
+
+impl Operator for Sub {
+    fn symbol(&self) -> &'static str {
+        "-"
+    }
+
+    fn apply(&self, left: i64, right: i64) -> Option<i64> {
+        left.checked_sub(right)
+    }
+
+    fn invert(&self, result: i64, right: i64) -> Inverse {
+        Inverse::from_option(result.checked_add(right))
+    }
+}
+
+impl Operator for Xor {
+    fn symbol(&self) -> &'static str {
+        "^"
+    }
+
+    fn apply(&self, left: i64, right: i64) -> Option<i64> {
+        Some(left ^ right)
+    }
+
+    fn invert(&self, result: i64, right: i64) -> Inverse {
+        Inverse::Exactly(result ^ right)
+    }
+}
+
+pub const PART_ONE: [&dyn Operator; 2] = [&Add, &Mul];
+pub const PART_TWO: [&dyn Operator; 3] = [&Add, &Mul, &Concat];
+const ALL: [&dyn Operator; 5] = [&Add, &Mul, &Concat, &Sub, &Xor];
+
+/// Whether every partial value of `terms` stays at least the first term
+/// and so can't come back down to the result once above it. Holds for the
+/// puzzle's operators as long as no term is negative and none is multiplied
+/// by zero.
+pub fn monotone(terms: &[i64], ops: &[&dyn Operator]) -> bool {
+    terms[0] >= 0
+        && terms[1..]
+            .iter()
+            .all(|&term| ops.iter().all(|op| op.non_decreasing(term)))
+}
+
+/// Looks up an operator by its symbol.
+pub fn from_symbol(symbol: &str) -> Option<&'static dyn Operator> {
+    ALL.into_iter().find(|op| op.symbol() == symbol)
+}
+
+/// Writes the equation out, e.g. `81 + 40 * 27 = 3267`.
+pub fn format_expression(result: i64, terms: &[i64], ops: &[&dyn Operator]) -> String {
+    let mut expression = terms[0].to_string();
+    for (op, term) in ops.iter().zip(terms[1..].iter()) {
+        expression += &format!(" {} {}", op.symbol(), term);
+    }
+
+    format!("{} = {}", expression, result)
+}
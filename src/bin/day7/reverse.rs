@@ -0,0 +1,90 @@
+use crate::operator::{self, Inverse, Operator};
+
+/// Finds operators like `find_operators`, but works backwards from
+/// `result`: the last term is undone with each operator's inverse, then the
+/// one before it and so on until only the first term is left. Inverses that
+/// don't exist, like a division with a remainder or a suffix that doesn't
+/// match, cut off whole subtrees at once.
+pub fn find_operators_reverse<'o>(
+    result: i64,
+    terms: &[i64],
+    ops: &[&'o dyn Operator],
+) -> Option<Vec<&'o dyn Operator>> {
+    let prune = operator::monotone(terms, ops);
+    let mut chosen = Vec::with_capacity(terms.len());
+
+    if unwind(result, terms, ops, prune, &mut chosen) {
+        // Operators were picked from the last one backwards
+        chosen.reverse();
+        Some(chosen)
+    } else {
+        None
+    }
+}
+
+fn unwind<'o>(
+    target: i64,
+    terms: &[i64],
+    ops: &[&'o dyn Operator],
+    prune: bool,
+    chosen: &mut Vec<&'o dyn Operator>,
+) -> bool {
+    let [rest @ .., last] = terms else {
+        return false;
+    };
+    if rest.is_empty() {
+        return *last == target;
+    }
+
+    for op in ops {
+        let left = match op.invert(target, *last) {
+            Inverse::Impossible => continue,
+            // Every partial value is at least the first term
+            Inverse::Exactly(left) if prune && left < rest[0] => continue,
+            Inverse::Exactly(left) => left,
+            Inverse::Any => {
+                // The terms before only have to evaluate to something
+                let mut before = Vec::with_capacity(rest.len());
+                if evaluate_any(rest[0], &rest[1..], ops, &mut before) {
+                    chosen.push(*op);
+                    chosen.extend(before.into_iter().rev());
+                    return true;
+                }
+                continue;
+            }
+        };
+
+        chosen.push(*op);
+        if unwind(left, rest, ops, prune, chosen) {
+            return true;
+        }
+        chosen.pop();
+    }
+
+    false
+}
+
+/// Picks operators, first to last, under which `terms` evaluate without
+/// overflowing or hitting an undefined operation.
+fn evaluate_any<'o>(
+    value: i64,
+    terms: &[i64],
+    ops: &[&'o dyn Operator],
+    chosen: &mut Vec<&'o dyn Operator>,
+) -> bool {
+    let [next, rest @ ..] = terms else {
+        return true;
+    };
+
+    for op in ops {
+        if let Some(next_value) = op.apply(value, *next) {
+            chosen.push(*op);
+            if evaluate_any(next_value, rest, ops, chosen) {
+                return true;
+            }
+            chosen.pop();
+        }
+    }
+
+    false
+}
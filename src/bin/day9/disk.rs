@@ -0,0 +1,369 @@
+use std::{cmp::Reverse, collections::BinaryHeap, fmt::Display, str::FromStr};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParseDiskMapError {
+    #[error("invalid digit {0:?} at position {1}")]
+    InvalidDigit(char, usize),
+}
+
+/// A contiguous run of blocks, either belonging to a file or free (`file == None`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub file: Option<usize>,
+    pub start: usize,
+    pub len: usize,
+}
+
+impl Segment {
+    pub fn end(&self) -> usize {
+        self.start + self.len
+    }
+
+    pub fn checksum(&self) -> usize {
+        // Sum of id * position over start..end
+        self.file.map_or(0, |id| {
+            id * (self.start * self.len + self.len * self.len.saturating_sub(1) / 2)
+        })
+    }
+}
+
+/// Disk layout stored as segments ordered by position, covering the whole
+/// disk without gaps. Adjacent segments never share the same owner.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DiskMap {
+    segments: Vec<Segment>,
+}
+
+impl FromStr for DiskMap {
+    type Err = ParseDiskMapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut disk = DiskMap::default();
+        let mut start = 0;
+
+        for (idx, c) in s.trim_end().chars().enumerate() {
+            let len = c
+                .to_digit(10)
+                .ok_or(ParseDiskMapError::InvalidDigit(c, idx))? as usize;
+            let file = (idx % 2 == 0).then_some(idx / 2);
+            disk.push(Segment { file, start, len });
+            start += len;
+        }
+
+        Ok(disk)
+    }
+}
+
+/// Renders the disk like the puzzle does, one character per block: file ids
+/// below ten as digits, larger ids as `[id]` and free blocks as `.`.
+impl Display for DiskMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for segment in self.segments.iter() {
+            let block = match segment.file {
+                None => ".".to_string(),
+                Some(id) if id < 10 => id.to_string(),
+                Some(id) => format!("[{}]", id),
+            };
+            write!(f, "{}", block.repeat(segment.len))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl DiskMap {
+    /// Appends a segment at the end of the disk, merging it with the last
+    /// segment if both belong to the same owner.
+    fn push(&mut self, segment: Segment) {
+        if segment.len == 0 {
+            return;
+        }
+
+        match self.segments.last_mut() {
+            Some(last) if last.file == segment.file && last.end() == segment.start => {
+                last.len += segment.len
+            }
+            _ => self.segments.push(segment),
+        }
+    }
+
+    /// Builds a disk from file segments, filling everything in between with
+    /// free space up to `len` blocks.
+    pub fn from_files(mut files: Vec<Segment>, len: usize) -> Self {
+        files.sort_unstable_by_key(|s| s.start);
+
+        let mut disk = DiskMap::default();
+        let mut pos = 0;
+        for file in files {
+            disk.push(Segment {
+                file: None,
+                start: pos,
+                len: file.start - pos,
+            });
+            disk.push(file);
+            pos = file.end();
+        }
+        disk.push(Segment {
+            file: None,
+            start: pos,
+            len: len - pos,
+        });
+
+        disk
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &Segment> {
+        self.segments.iter().filter(|s| s.file.is_some())
+    }
+
+    pub fn free_runs(&self) -> impl Iterator<Item = &Segment> {
+        self.segments.iter().filter(|s| s.file.is_none())
+    }
+
+    /// Encodes the layout back into the dense digit format. Runs longer than
+    /// nine blocks are split using zero-length runs of the other kind.
+    ///
+    /// The dense format numbers files by their position, so parsing the
+    /// result renumbers files that are out of order or split into several
+    /// runs. Zero-length files in the parsed input are not preserved either.
+    pub fn encode(&self) -> String {
+        let mut dense = String::new();
+        // The dense format alternates file and free runs, starting with a file
+        let mut expect_file = true;
+
+        for segment in self.segments.iter() {
+            let is_file = segment.file.is_some();
+            let mut len = segment.len;
+            while len > 0 {
+                if is_file != expect_file {
+                    dense.push('0');
+                    expect_file = !expect_file;
+                }
+
+                let run = len.min(9);
+                dense.push(char::from_digit(run as u32, 10).unwrap());
+                expect_file = !expect_file;
+                len -= run;
+            }
+        }
+
+        dense
+    }
+
+    /// Total number of blocks on the disk.
+    pub fn len(&self) -> usize {
+        self.segments.last().map_or(0, |s| s.end())
+    }
+
+    pub fn checksum(&self) -> usize {
+        self.segments.iter().map(Segment::checksum).sum()
+    }
+
+    /// Moves file blocks one at a time from the end of the disk into the
+    /// leftmost free block, fragmenting files as needed.
+    pub fn compact_blocks(&self) -> DiskMap {
+        let mut remaining = self.segments.clone();
+        let mut compacted = DiskMap::default();
+        let mut front = 0;
+
+        while front < remaining.len() {
+            let segment = remaining[front];
+            front += 1;
+
+            if segment.file.is_some() {
+                compacted.push(segment);
+                continue;
+            }
+
+            let mut pos = segment.start;
+            while pos < segment.end() && front < remaining.len() {
+                let back = remaining.last_mut().unwrap();
+                if back.file.is_none() {
+                    remaining.pop();
+                    continue;
+                }
+
+                let moved = back.len.min(segment.end() - pos);
+                compacted.push(Segment {
+                    file: back.file,
+                    start: pos,
+                    len: moved,
+                });
+                pos += moved;
+                back.len -= moved;
+                if back.len == 0 {
+                    remaining.pop();
+                }
+            }
+        }
+
+        let end = compacted.len();
+        compacted.push(Segment {
+            file: None,
+            start: end,
+            len: self.len() - end,
+        });
+
+        compacted
+    }
+
+    /// Moves whole files, highest id first, into the leftmost free run that
+    /// fits them.
+    pub fn compact_files(&self) -> DiskMap {
+        self.compact_files_with(FreeRuns::leftmost_fit)
+    }
+
+    /// Moves whole files, highest id first, into the free run picked by
+    /// `choose`. Files stay in place when `choose` returns `None`.
+    pub fn compact_files_with(
+        &self,
+        mut choose: impl FnMut(&FreeRuns, &Segment) -> Option<(usize, usize)>,
+    ) -> DiskMap {
+        let mut free_runs = FreeRuns::new(self.free_runs());
+
+        let mut files = self.files().copied().collect::<Vec<_>>();
+        files.sort_unstable_by_key(|s| Reverse(s.file));
+
+        for file in files.iter_mut() {
+            if let Some((start, len)) = choose(&free_runs, file) {
+                free_runs.allocate(start, len, file.len);
+                file.start = start;
+            }
+        }
+
+        DiskMap::from_files(files, self.len())
+    }
+}
+
+/// Free runs kept in one min-heap of start positions per run length, so a
+/// fit for a file only has to peek at one heap per candidate length.
+pub struct FreeRuns {
+    by_len: Vec<BinaryHeap<Reverse<usize>>>,
+}
+
+impl FreeRuns {
+    fn new<'a>(runs: impl Iterator<Item = &'a Segment>) -> Self {
+        let mut by_len: Vec<BinaryHeap<Reverse<usize>>> = vec![];
+        for run in runs {
+            if by_len.len() <= run.len {
+                by_len.resize(run.len + 1, BinaryHeap::new());
+            }
+            by_len[run.len].push(Reverse(run.start));
+        }
+
+        Self { by_len }
+    }
+
+    /// Leftmost run of each length that fits `file` and lies before it, as
+    /// `(start, len)` ordered by length.
+    fn candidates<'a>(
+        &'a self,
+        file: &'a Segment,
+    ) -> impl DoubleEndedIterator<Item = (usize, usize)> + 'a {
+        (file.len..self.by_len.len()).filter_map(move |len| {
+            self.by_len[len]
+                .peek()
+                .filter(|Reverse(start)| *start < file.start)
+                .map(|&Reverse(start)| (start, len))
+        })
+    }
+
+    pub fn leftmost_fit(&self, file: &Segment) -> Option<(usize, usize)> {
+        self.candidates(file).min()
+    }
+
+    pub fn smallest_fit(&self, file: &Segment) -> Option<(usize, usize)> {
+        self.candidates(file).next()
+    }
+
+    pub fn largest_fit(&self, file: &Segment) -> Option<(usize, usize)> {
+        self.candidates(file).next_back()
+    }
+
+    /// Takes `used` blocks from the front of the run at `start` of length `len`.
+    fn allocate(&mut self, start: usize, len: usize, used: usize) {
+        assert_eq!(self.by_len[len].pop(), Some(Reverse(start)));
+        if len > used {
+            self.by_len[len - used].push(Reverse(start + used));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn example_layouts() {
+        let disk = "12345".parse::<DiskMap>().unwrap();
+        assert_eq!(disk.to_string(), "0..111....22222");
+        assert_eq!(disk.compact_blocks().to_string(), "022111222......");
+
+        let disk = "2333133121414131402".parse::<DiskMap>().unwrap();
+        assert_eq!(
+            disk.to_string(),
+            "00...111...2...333.44.5555.6666.777.888899"
+        );
+        assert_eq!(
+            disk.compact_blocks().to_string(),
+            "0099811188827773336446555566.............."
+        );
+        assert_eq!(
+            disk.compact_files().to_string(),
+            "00992111777.44.333....5555.6666.....8888.."
+        );
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        for dense in ["12345", "2333133121414131402"] {
+            assert_eq!(dense.parse::<DiskMap>().unwrap().encode(), dense);
+        }
+
+        let compacted = "2333133121414131402"
+            .parse::<DiskMap>()
+            .unwrap()
+            .compact_files();
+        assert_eq!(compacted.encode(), "20201030312134414542");
+        assert_eq!(
+            compacted.encode().parse::<DiskMap>().unwrap().checksum(),
+            "00112333444.55.666....7777.8888.....9999.."
+                .chars()
+                .enumerate()
+                .filter_map(|(i, c)| c.to_digit(10).map(|id| i * id as usize))
+                .sum()
+        );
+
+        let long_runs = DiskMap::from_files(
+            vec![Segment {
+                file: Some(0),
+                start: 0,
+                len: 12,
+            }],
+            25,
+        );
+        assert_eq!(long_runs.encode(), "903904");
+    }
+
+    #[test]
+    fn example_checksums() {
+        let disk = "2333133121414131402".parse::<DiskMap>().unwrap();
+
+        assert_eq!(disk.compact_blocks().checksum(), 1928);
+        assert_eq!(disk.compact_files().checksum(), 2858);
+    }
+
+    #[test]
+    fn file_ids_beyond_u16() {
+        // 70000 single-block files each followed by one free block
+        let disk = "11".repeat(70_000).parse::<DiskMap>().unwrap();
+        let compacted = disk.compact_files();
+
+        assert_eq!(compacted.len(), 140_000);
+        assert_eq!(compacted.checksum(), disk.compact_blocks().checksum());
+    }
+}
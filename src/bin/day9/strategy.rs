@@ -0,0 +1,245 @@
+use std::collections::BTreeMap;
+
+use crate::disk::{DiskMap, Segment};
+
+pub trait CompactionStrategy {
+    fn name(&self) -> &'static str;
+
+    fn compact(&self, disk: &DiskMap) -> DiskMap;
+}
+
+/// Moves single blocks from the end into the leftmost free block (first star).
+pub struct BlockByBlock;
+
+/// Moves whole files into the leftmost free run that fits (second star).
+pub struct FirstFit;
+
+/// Moves whole files into the smallest free run that fits, leftmost on ties.
+pub struct BestFit;
+
+/// Moves whole files into the largest free run that fits, leftmost on ties.
+pub struct WorstFit;
+
+/// Like `FirstFit`, but each search resumes after the previous placement and
+/// wraps around to the start of the disk.
+pub struct NextFit;
+
+impl CompactionStrategy for BlockByBlock {
+    fn name(&self) -> &'static str {
+        "block-by-block"
+    }
+
+    fn compact(&self, disk: &DiskMap) -> DiskMap {
+        disk.compact_blocks()
+    }
+}
+
+impl CompactionStrategy for FirstFit {
+    fn name(&self) -> &'static str {
+        "first-fit"
+    }
+
+    fn compact(&self, disk: &DiskMap) -> DiskMap {
+        disk.compact_files()
+    }
+}
+
+impl CompactionStrategy for BestFit {
+    fn name(&self) -> &'static str {
+        "best-fit"
+    }
+
+    fn compact(&self, disk: &DiskMap) -> DiskMap {
+        disk.compact_files_with(|free_runs, file| free_runs.smallest_fit(file))
+    }
+}
+
+impl CompactionStrategy for WorstFit {
+    fn name(&self) -> &'static str {
+        "worst-fit"
+    }
+
+    fn compact(&self, disk: &DiskMap) -> DiskMap {
+        disk.compact_files_with(|free_runs, file| free_runs.largest_fit(file))
+    }
+}
+
+impl CompactionStrategy for NextFit {
+    fn name(&self) -> &'static str {
+        "next-fit"
+    }
+
+    fn compact(&self, disk: &DiskMap) -> DiskMap {
+        // Free runs by start position
+        let mut free_runs = disk
+            .free_runs()
+            .map(|s| (s.start, s.len))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut files = disk.files().copied().collect::<Vec<_>>();
+        files.sort_unstable_by_key(|s| std::cmp::Reverse(s.file));
+
+        let mut cursor = 0;
+        for file in files.iter_mut() {
+            let fits = |(&start, &len): (&usize, &usize)| {
+                (start < file.start && len >= file.len).then_some((start, len))
+            };
+
+            let found = free_runs
+                .range(cursor..)
+                .find_map(fits)
+                .or_else(|| free_runs.range(..cursor).find_map(fits));
+
+            if let Some((start, len)) = found {
+                free_runs.remove(&start);
+                if len > file.len {
+                    free_runs.insert(start + file.len, len - file.len);
+                }
+                file.start = start;
+                cursor = start + file.len;
+            }
+        }
+
+        DiskMap::from_files(files, disk.len())
+    }
+}
+
+pub const STRATEGIES: [&dyn CompactionStrategy; 5] =
+    [&BlockByBlock, &FirstFit, &BestFit, &WorstFit, &NextFit];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FragmentationStats {
+    pub free_runs: usize,
+    pub largest_free_run: usize,
+    pub fragmented_files: usize,
+    pub moved_blocks: usize,
+    pub checksum: usize,
+}
+
+impl FragmentationStats {
+    pub fn new(before: &DiskMap, after: &DiskMap) -> Self {
+        let file_count = after
+            .files()
+            .filter_map(|s| s.file)
+            .max()
+            .map_or(0, |id| id + 1);
+        let mut file_runs = vec![0usize; file_count];
+        for id in after.files().filter_map(|s| s.file) {
+            file_runs[id] += 1;
+        }
+
+        Self {
+            free_runs: after.free_runs().count(),
+            largest_free_run: after.free_runs().map(|s| s.len).max().unwrap_or(0),
+            fragmented_files: file_runs.iter().filter(|&&runs| runs > 1).count(),
+            moved_blocks: moved_blocks(before.segments(), after.segments()),
+            checksum: after.checksum(),
+        }
+    }
+}
+
+/// Number of file blocks whose owner differs between the two layouts.
+fn moved_blocks(before: &[Segment], after: &[Segment]) -> usize {
+    let file_blocks = before
+        .iter()
+        .filter(|s| s.file.is_some())
+        .map(|s| s.len)
+        .sum::<usize>();
+
+    let (mut i, mut j) = (0, 0);
+    let mut unmoved = 0;
+    while i < before.len() && j < after.len() {
+        let (a, b) = (before[i], after[j]);
+        if a.file.is_some() && a.file == b.file {
+            unmoved += a.end().min(b.end()).saturating_sub(a.start.max(b.start));
+        }
+
+        if a.end() <= b.end() {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+
+    file_blocks - unmoved
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stats(strategy: &dyn CompactionStrategy, dense: &str) -> (String, FragmentationStats) {
+        let disk = dense.parse::<DiskMap>().unwrap();
+        let compacted = strategy.compact(&disk);
+        (
+            compacted.to_string(),
+            FragmentationStats::new(&disk, &compacted),
+        )
+    }
+
+    #[test]
+    fn example_strategies() {
+        let example = "2333133121414131402";
+
+        assert_eq!(
+            stats(&BlockByBlock, example),
+            (
+                "0099811188827773336446555566..............".to_string(),
+                FragmentationStats {
+                    free_runs: 1,
+                    largest_free_run: 14,
+                    fragmented_files: 2,
+                    moved_blocks: 12,
+                    checksum: 1928,
+                }
+            )
+        );
+
+        // Every free run fits at most one file here, so all whole file
+        // strategies agree
+        for strategy in [
+            &FirstFit as &dyn CompactionStrategy,
+            &BestFit,
+            &WorstFit,
+            &NextFit,
+        ] {
+            assert_eq!(
+                stats(strategy, example),
+                (
+                    "00992111777.44.333....5555.6666.....8888..".to_string(),
+                    FragmentationStats {
+                        free_runs: 6,
+                        largest_free_run: 5,
+                        fragmented_files: 0,
+                        moved_blocks: 8,
+                        checksum: 2858,
+                    }
+                ),
+                "{}",
+                strategy.name()
+            );
+        }
+    }
+
+    #[test]
+    fn strategies_differ() {
+        let layout = |strategy: &dyn CompactionStrategy, dense: &str| stats(strategy, dense).0;
+
+        // 0...1..23
+        assert_eq!(layout(&FirstFit, "1312101"), "0321.....");
+        assert_eq!(layout(&BestFit, "1312101"), "01...32..");
+
+        // 0.1...23
+        assert_eq!(layout(&FirstFit, "1113101"), "0312....");
+        assert_eq!(layout(&WorstFit, "1113101"), "01.32...");
+
+        // 0.1..2.344, file 3 goes after file 4 and file 2 wraps around
+        assert_eq!(layout(&FirstFit, "111211102"), "031442....");
+        assert_eq!(layout(&NextFit, "111211102"), "02144.3...");
+
+        let (_, stats) = stats(&NextFit, "111211102");
+        assert_eq!(stats.moved_blocks, 4);
+        assert_eq!(stats.free_runs, 2);
+        assert_eq!(stats.largest_free_run, 3);
+    }
+}
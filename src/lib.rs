@@ -1,2 +1,3 @@
+pub mod bigint;
 pub mod input;
 pub mod utils;
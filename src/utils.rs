@@ -22,6 +22,43 @@ impl Direction {
         }
     }
 
+    pub fn turn_left(&self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Down => Direction::Right,
+            Direction::Left => Direction::Down,
+            Direction::Right => Direction::Up,
+        }
+    }
+
+    pub fn turn_right(&self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+            Direction::Right => Direction::Down,
+        }
+    }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+
+    /// Moves `coord` one step, returns `None` if that leaves `bounds`.
+    pub fn step(&self, coord: Coord, bounds: (usize, usize)) -> Option<Coord> {
+        match self {
+            Direction::Up => coord.0.checked_sub(1).map(|row| (row, coord.1)),
+            Direction::Down => (coord.0 + 1 < bounds.0).then_some((coord.0 + 1, coord.1)),
+            Direction::Left => coord.1.checked_sub(1).map(|col| (coord.0, col)),
+            Direction::Right => (coord.1 + 1 < bounds.1).then_some((coord.0, coord.1 + 1)),
+        }
+    }
+
     pub fn to_coord_vec(&self) -> CoordVec {
         CoordVec(match self {
             Direction::Up => (-1, 0),
@@ -63,6 +100,7 @@ impl CoordVec {
     }
 }
 
+#[derive(Clone)]
 pub struct Grid<T: Copy + Default> {
     data: Vec<T>,
     rows: usize,
@@ -122,6 +160,35 @@ impl<T: Copy + Default> Grid<T> {
             index: (0, 0),
         }
     }
+
+    /// Replaces every cell with a `height` x `width` block. `block` returns
+    /// the tiles of a cell's block in row-major order, or an error to abort.
+    pub fn expand<U, I, E>(
+        &self,
+        (height, width): (usize, usize),
+        mut block: impl FnMut(Coord, &T) -> Result<I, E>,
+    ) -> Result<Grid<U>, E>
+    where
+        U: Copy + Default,
+        I: IntoIterator<Item = U>,
+    {
+        let mut expanded = Grid::zeros(self.rows * height, self.cols * width);
+
+        for (coord, value) in self.indexed_iter() {
+            let mut count = 0;
+            for (i, tile) in block(coord, value)?.into_iter().enumerate() {
+                assert!(i < height * width, "block for {:?} is too large", coord);
+                expanded.set(
+                    (coord.0 * height + i / width, coord.1 * width + i % width),
+                    tile,
+                );
+                count += 1;
+            }
+            assert_eq!(count, height * width, "block for {:?} is too small", coord);
+        }
+
+        Ok(expanded)
+    }
 }
 
 pub struct IndexedGridIterator<'a, T: Copy + Default> {
@@ -177,3 +244,48 @@ pub fn pause() {
     stdout.flush().unwrap();
     stdin().read(&mut [0, 0]).unwrap();
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn turns_cycle() {
+        let clockwise = [
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+            Direction::Left,
+        ];
+        for (i, &dir) in clockwise.iter().enumerate() {
+            let next = clockwise[(i + 1) % 4];
+            assert_eq!(dir.turn_right(), next);
+            assert_eq!(next.turn_left(), dir);
+            assert_eq!(dir.turn_right().turn_right(), dir.opposite());
+            assert_eq!(dir.turn_left().turn_left(), dir.opposite());
+            assert_eq!(dir.opposite().opposite(), dir);
+            assert_ne!(dir.opposite(), dir);
+        }
+    }
+
+    #[test]
+    fn step_within_bounds() {
+        let bounds = (3, 5);
+        let (max_row, max_col) = (bounds.0 - 1, bounds.1 - 1);
+
+        assert_eq!(Direction::Up.step((0, 2), bounds), None);
+        assert_eq!(Direction::Up.step((max_row, 2), bounds), Some((1, 2)));
+        assert_eq!(Direction::Down.step((0, 2), bounds), Some((1, 2)));
+        assert_eq!(Direction::Down.step((max_row, 2), bounds), None);
+        assert_eq!(Direction::Left.step((1, 0), bounds), None);
+        assert_eq!(Direction::Left.step((1, max_col), bounds), Some((1, 3)));
+        assert_eq!(Direction::Right.step((1, 0), bounds), Some((1, 1)));
+        assert_eq!(Direction::Right.step((1, max_col), bounds), None);
+
+        // A step there and back is a no-op away from the edges
+        for dir in DIRECTIONS {
+            let next = dir.step((1, 2), bounds).unwrap();
+            assert_eq!(dir.opposite().step(next, bounds), Some((1, 2)));
+        }
+    }
+}